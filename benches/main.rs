@@ -26,7 +26,7 @@ fn table(bencher: divan::Bencher, size: usize) {
                     TemplateParameters::from([(
                         "cell",
                         TemplateParametersValue::ValuesVec(
-                            (0..size).map(move |x| (x + y * size).to_string()).collect(),
+                            (0..size).map(move |x| Scalar::from(x + y * size)).collect(),
                         ),
                     )])
                 })
@@ -36,3 +36,66 @@ fn table(bencher: divan::Bencher, size: usize) {
 
     bencher.bench(|| table_template.render(&parameters, &templates).unwrap());
 }
+
+#[divan::bench(args=[10, 100, 1000])]
+fn table_to_writer(bencher: divan::Bencher, size: usize) {
+    let parser = drunk_snail::Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+
+    let table_template = parser
+        .parse("<table>\n    <!-- (ref)Row -->\n</table>")
+        .unwrap();
+    let row_template = parser
+        .parse("<tr>\n    <td><!-- (param)cell --></td>\n</tr>")
+        .unwrap();
+    let templates = Templates::from([("Row", row_template)]);
+
+    let parameters = TemplateParameters::from([(
+        "Row",
+        TemplateParametersValue::ParametersVec(
+            (0..size)
+                .map(move |y| {
+                    TemplateParameters::from([(
+                        "cell",
+                        TemplateParametersValue::ValuesVec(
+                            (0..size).map(move |x| Scalar::from(x + y * size)).collect(),
+                        ),
+                    )])
+                })
+                .collect(),
+        ),
+    )]);
+
+    bencher.bench(|| {
+        table_template
+            .render_to_writer(&parameters, &templates, std::io::sink())
+            .unwrap()
+    });
+}
+
+fn parse_bench_text(size: usize) -> String {
+    (0..size)
+        .map(|y| {
+            (0..size)
+                .map(|x| format!("<td><!-- (optional)(param)cell_{x}_{y} --></td>"))
+                .collect::<String>()
+                + "\n    <!-- (ref)Row -->\n"
+        })
+        .collect()
+}
+
+#[divan::bench(args=[10, 100, 300])]
+fn parse(bencher: divan::Bencher, size: usize) {
+    let parser = drunk_snail::Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let text = parse_bench_text(size);
+
+    bencher.bench(|| parser.parse(&text).unwrap());
+}
+
+#[cfg(feature = "regex")]
+#[divan::bench(args=[10, 100, 300])]
+fn parse_regex(bencher: divan::Bencher, size: usize) {
+    let parser = drunk_snail::RegexParser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let text = parse_bench_text(size);
+
+    bencher.bench(|| parser.parse(&text).unwrap());
+}
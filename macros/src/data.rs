@@ -0,0 +1,85 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{Data, DeriveInput, Error, Fields, LitStr};
+
+fn parameter_name(field: &syn::Field) -> syn::Result<String> {
+    let mut result = None;
+    for attribute in &field.attrs {
+        if !attribute.path().is_ident("snail") {
+            continue;
+        }
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                result = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename = \"...\"`"))
+            }
+        })?;
+    }
+    match (result, &field.ident) {
+        (Some(name), _) => Ok(name),
+        (None, Some(identifier)) => Ok(identifier.unraw().to_string()),
+        (None, None) => Err(Error::new_spanned(
+            field,
+            "tuple struct fields need #[snail(rename = \"...\")]",
+        )),
+    }
+}
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "TemplateData can only be derived for structs",
+        ));
+    };
+    let fields = match &data.fields {
+        Fields::Named(fields) => fields.named.iter().collect(),
+        Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+        Fields::Unit => Vec::new(),
+    };
+    let mut arms = Vec::new();
+    let mut presence = Vec::new();
+    for (index, field) in fields.into_iter().enumerate() {
+        let name = parameter_name(field)?;
+        let member = match &field.ident {
+            Some(identifier) => quote! { #identifier },
+            None => {
+                let index = syn::Index::from(index);
+                quote! { #index }
+            }
+        };
+        arms.push(quote! {
+            #name => ::drunk_snail::TemplateValue::to_value_ref(&self.#member),
+        });
+        presence.push(quote! {
+            if ::drunk_snail::TemplateValue::is_present(&self.#member) {
+                names.push(#name);
+            }
+        });
+    }
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::drunk_snail::TemplateData for #name #type_generics #where_clause {
+            fn get(&self, name: &str) -> Option<::drunk_snail::ValueRef<'_>> {
+                match name {
+                    #(#arms)*
+                    _ => None,
+                }
+            }
+            fn names(&self) -> Vec<&str> {
+                let mut names = Vec::new();
+                #(#presence)*
+                names
+            }
+        }
+        impl #impl_generics ::drunk_snail::TemplateValue for #name #type_generics #where_clause {
+            fn to_value_ref(&self) -> Option<::drunk_snail::ValueRef<'_>> {
+                Some(::drunk_snail::ValueRef::Data(self))
+            }
+        }
+    })
+}
@@ -0,0 +1,643 @@
+//! Compile-time [drunk_snail](https://docs.rs/drunk_snail) templates and
+//! `#[derive(TemplateData)]`.
+//!
+//! ```ignore
+//! drunk_snail_macros::template!(Table, "templates/table.html", lists = [cell]);
+//!
+//! let html = Table {
+//!     Row: vec![table::Row {
+//!         cell: vec!["1".to_string(), "2".to_string()],
+//!     }],
+//! }
+//! .render();
+//! ```
+//!
+//! The template path is relative to the crate root. Referenced templates are looked up
+//! next to it by file stem, preferring the extension of the referencing template, and
+//! each gets a struct named after it in a module named after the root struct in
+//! `snake_case`, `table::Row` here. Parameters become `String` fields, `Option<String>`
+//! when every tag using them is optional and `Vec<String>` when listed in `lists`;
+//! references become `Vec` of the referenced template struct.
+//!
+//! Rendering follows [`Template::render`](drunk_snail::Template::render), except that an
+//! empty `Vec<String>` renders like an absent optional parameter instead of failing.
+//! Generated code names `::drunk_snail`, so the crate using the macro depends on it too.
+
+mod data;
+
+use drunk_snail::{
+    Field, FieldKind, Line, LineEnding, ParametersLineToken, Parser, ReferenceTag, Syntax, Template,
+};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{DeriveInput, Error, Ident, LitStr, Token, bracketed, parse_macro_input};
+
+struct Input {
+    name: Ident,
+    path: LitStr,
+    lists: BTreeSet<String>,
+    syntax: BTreeMap<String, LitStr>,
+}
+
+const SYNTAX_KEYS: [&str; 8] = [
+    "open_tag",
+    "close_tag",
+    "optional_operator",
+    "escape_operator",
+    "raw_operator",
+    "indent_operator",
+    "parameter_operator",
+    "reference_operator",
+];
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let path = input.parse()?;
+        let mut lists = BTreeSet::new();
+        let mut syntax = BTreeMap::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            if key == "lists" {
+                let content;
+                bracketed!(content in input);
+                for name in Punctuated::<Ident, Token![,]>::parse_terminated(&content)? {
+                    lists.insert(name.to_string());
+                }
+            } else if SYNTAX_KEYS.contains(&key.to_string().as_str()) {
+                syntax.insert(key.to_string(), input.parse()?);
+            } else {
+                return Err(Error::new(
+                    key.span(),
+                    format!(
+                        "Unknown argument \"{key}\", expected \"lists\" or one of {}",
+                        SYNTAX_KEYS.join(", ")
+                    ),
+                ));
+            }
+        }
+        Ok(Input {
+            name,
+            path,
+            lists,
+            syntax,
+        })
+    }
+}
+
+/// Template file loaded by the macro, `name` is `None` for the root one.
+struct Source {
+    name: Option<String>,
+    path: PathBuf,
+    text: String,
+}
+
+fn identifier(name: &str, span: Span) -> syn::Result<Ident> {
+    syn::parse_str::<Ident>(name)
+        .or_else(|_| syn::parse_str::<Ident>(&format!("r#{name}")))
+        .map(|mut identifier| {
+            identifier.set_span(span);
+            identifier
+        })
+        .map_err(|_| Error::new(span, format!("\"{name}\" is not a valid Rust identifier")))
+}
+
+fn sibling(path: &Path, name: &str) -> Option<PathBuf> {
+    let directory = path.parent()?;
+    if let Some(extension) = path.extension() {
+        let candidate = directory.join(name).with_extension(extension);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+    }
+    let mut candidates: Vec<PathBuf> = fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|candidate| {
+            candidate.is_file() && candidate.file_stem().is_some_and(|stem| stem == name)
+        })
+        .collect();
+    candidates.sort();
+    candidates.into_iter().next()
+}
+
+/// Root template source followed by every template reachable from it.
+fn load(input: &Input, parser: &Parser) -> syn::Result<Vec<Source>> {
+    let span = input.path.span();
+    let root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default())
+        .join(input.path.value());
+    let mut sources = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::from([(None, root)]);
+    while let Some((name, path)) = queue.pop_front() {
+        let text = fs::read_to_string(&path).map_err(|error| {
+            Error::new(
+                span,
+                format!("Can not read \"{}\": {error}", path.display()),
+            )
+        })?;
+        let template = parser.parse(&text).map_err(|error| {
+            Error::new(
+                span,
+                format!("Can not parse \"{}\": {error}", path.display()),
+            )
+        })?;
+        for ReferenceTag {
+            name: reference,
+            is_optional,
+            line,
+            column,
+        } in template.references()
+        {
+            if seen.contains(reference) {
+                continue;
+            }
+            match sibling(&path, reference) {
+                Some(reference_path) => {
+                    seen.insert(reference.to_string());
+                    queue.push_back((Some(reference.to_string()), reference_path));
+                }
+                None if is_optional => {}
+                None => {
+                    return Err(Error::new(
+                        span,
+                        format!(
+                            "Unresolved reference \"{reference}\" at \"{}\", line {line}, column {column}",
+                            path.display()
+                        ),
+                    ));
+                }
+            }
+        }
+        drop(template);
+        sources.push(Source { name, path, text });
+    }
+    Ok(sources)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FieldType {
+    String,
+    Option,
+    Vec,
+    /// Reference to a loaded template.
+    Data,
+}
+
+fn field_type(name: &str, field: &Field, lists: &BTreeSet<String>) -> FieldType {
+    match field.kind {
+        FieldKind::Reference => FieldType::Data,
+        FieldKind::Value if lists.contains(name) => FieldType::Vec,
+        FieldKind::Value if field.is_optional => FieldType::Option,
+        FieldKind::Value => FieldType::String,
+    }
+}
+
+fn line_ending_tokens(line_ending: LineEnding) -> TokenStream {
+    match line_ending {
+        LineEnding::Lf => quote! { ::drunk_snail::LineEnding::Lf },
+        LineEnding::CrLf => quote! { ::drunk_snail::LineEnding::CrLf },
+        LineEnding::None => quote! { ::drunk_snail::LineEnding::None },
+    }
+}
+
+/// Generated code writes line endings like the runtime renderer: only once more output
+/// follows, so that unterminated lines are separated when repeated.
+/// `separator` of the generated code is `None` for the root template, whose unterminated
+/// lines are then separated like `previous` line.
+fn render_line(
+    line: &Line,
+    line_ending: LineEnding,
+    previous: LineEnding,
+    fields: &BTreeMap<&str, (Ident, FieldType)>,
+) -> TokenStream {
+    let previous = line_ending_tokens(previous);
+    let separator = quote! { separator.unwrap_or(#previous) };
+    let start_line = quote! {
+        ::drunk_snail::assemble::start_line(
+            result,
+            pending,
+            ::drunk_snail::LineEndings::Preserve,
+            #separator,
+        )
+        .expect("writing to a String does not fail");
+    };
+    // Templates rendered through a reference are separated like the reference line.
+    let reference_separator = match line_ending {
+        LineEnding::None => separator.clone(),
+        line_ending => line_ending_tokens(line_ending),
+    };
+    let line_ending = line_ending_tokens(line_ending);
+    match line {
+        Line::Raw { value } => quote! {
+            #start_line
+            result.push_str(left);
+            result.push_str(#value);
+            result.push_str(right);
+            *pending = Some(#line_ending);
+        },
+        Line::Parameters { tokens } => {
+            let all_tokens_are_optional = tokens.iter().all(|token| match token {
+                ParametersLineToken::Raw { .. } | ParametersLineToken::Reference { .. } => true,
+                ParametersLineToken::Parameter { is_optional, .. } => *is_optional,
+            });
+            let mut body = Vec::new();
+            let mut references = Vec::new();
+            for token in tokens {
+                match token {
+                    ParametersLineToken::Raw { value } => {
+                        body.push(quote! { result.push_str(#value); })
+                    }
+                    ParametersLineToken::Reference { name, .. } => {
+                        body.push(quote! { positions.push(result.len() - line_start); });
+                        references.push(match fields.get(name.as_ref()) {
+                            Some((field, _)) => quote! {{
+                                let mut output = String::new();
+                                let mut pending = None;
+                                for data in &self.#field {
+                                    data.render_lines(
+                                        &mut output,
+                                        escaper,
+                                        "",
+                                        "",
+                                        &mut pending,
+                                        Some(#reference_separator),
+                                    );
+                                }
+                                if let Some(line_ending) = pending {
+                                    output.push_str(line_ending.as_str());
+                                }
+                                ::drunk_snail::assemble::ReferenceLines::new(output)
+                            }},
+                            // Optional reference to a template that does not exist.
+                            None => quote! {
+                                ::drunk_snail::assemble::ReferenceLines::empty()
+                            },
+                        });
+                    }
+                    ParametersLineToken::Parameter {
+                        is_optional,
+                        is_raw,
+                        is_indent,
+                        name,
+                        ..
+                    } => {
+                        let (field, field_type) = &fields[name.as_ref()];
+                        let mut push = if *is_raw {
+                            quote! { result.push_str(value); }
+                        } else {
+                            quote! { result.push_str(&escaper.escape(value)); }
+                        };
+                        if *is_indent {
+                            push = quote! {
+                                let start = result.len() - line_start;
+                                #push
+                                indents.push(start..result.len() - line_start);
+                            };
+                        }
+                        let stop = !is_optional || all_tokens_are_optional;
+                        body.push(match field_type {
+                            FieldType::String => quote! {
+                                if index == 0 {
+                                    let value = self.#field.as_str();
+                                    #push
+                                }
+                                next = next && !#stop;
+                            },
+                            FieldType::Option => quote! {
+                                match &self.#field {
+                                    Some(value) => {
+                                        if index == 0 {
+                                            let value = value.as_str();
+                                            #push
+                                        }
+                                        next = next && !#stop;
+                                    }
+                                    None => next = false,
+                                }
+                            },
+                            FieldType::Vec => quote! {
+                                if let Some(value) = self.#field.get(index) {
+                                    let value = value.as_str();
+                                    #push
+                                }
+                                next = next && self.#field.len() > index + 1;
+                            },
+                            FieldType::Data => unreachable!("conflicts are rejected beforehand"),
+                        });
+                    }
+                }
+            }
+            let is_assembled = !references.is_empty()
+                || tokens.iter().any(|token| {
+                    matches!(
+                        token,
+                        ParametersLineToken::Parameter {
+                            is_indent: true,
+                            ..
+                        }
+                    )
+                });
+            if !is_assembled {
+                return quote! {
+                    let mut index = 0;
+                    loop {
+                        let mut next = true;
+                        #start_line
+                        result.push_str(left);
+                        #(#body)*
+                        result.push_str(right);
+                        *pending = Some(#line_ending);
+                        if !next {
+                            break;
+                        }
+                        index += 1;
+                    }
+                };
+            }
+            // Assembled at the end of `result` and written again like the runtime renderer does.
+            quote! {
+                let references = [#(#references),*];
+                let mut index = 0;
+                loop {
+                    let mut next = true;
+                    let line_start = result.len();
+                    let mut positions: Vec<usize> = Vec::new();
+                    let mut indents: Vec<::std::ops::Range<usize>> = Vec::new();
+                    result.push_str(left);
+                    #(#body)*
+                    result.push_str(right);
+                    let line = result.split_off(line_start);
+                    ::drunk_snail::assemble::push_assembled_line(
+                        result,
+                        pending,
+                        ::drunk_snail::LineEndings::Preserve,
+                        &line,
+                        &positions,
+                        &indents,
+                        &references,
+                        #line_ending,
+                        #separator,
+                    )
+                    .expect("writing to a String does not fail");
+                    if !next {
+                        break;
+                    }
+                    index += 1;
+                }
+            }
+        }
+        Line::Reference {
+            left: reference_left,
+            name,
+            right: reference_right,
+            ..
+        } => match fields.get(name.as_ref()) {
+            Some((field, _)) => {
+                let reference_left = reference_left.as_deref().unwrap_or("");
+                let reference_right = reference_right.as_deref().unwrap_or("");
+                quote! {
+                    {
+                        let left = [left, #reference_left].concat();
+                        let right = [right, #reference_right].concat();
+                        for data in &self.#field {
+                            data.render_lines(
+                                result,
+                                escaper,
+                                &left,
+                                &right,
+                                pending,
+                                Some(#reference_separator),
+                            );
+                            if *pending == Some(::drunk_snail::LineEnding::None) {
+                                *pending = Some(#line_ending);
+                            }
+                        }
+                    }
+                }
+            }
+            // Optional reference to a template that does not exist renders nothing.
+            None => quote! {},
+        },
+    }
+}
+
+fn generate(
+    input: &Input,
+    struct_name: &Ident,
+    template: &Template,
+    names: &BTreeSet<&str>,
+    module: &TokenStream,
+) -> syn::Result<TokenStream> {
+    let span = input.path.span();
+    let schema = template.schema(&drunk_snail::Templates::new());
+    if let Some(conflict) = schema.conflicts.first() {
+        return Err(Error::new(
+            span,
+            format!(
+                "\"{}\" is used as a parameter at {} and as a reference at {}",
+                conflict.name, conflict.parameter, conflict.reference
+            ),
+        ));
+    }
+    let mut fields = BTreeMap::new();
+    let mut definitions = Vec::new();
+    for (name, field) in &schema.root {
+        let field_type = field_type(name, field, &input.lists);
+        if field_type == FieldType::Data && !names.contains(name.as_str()) {
+            continue;
+        }
+        let field_identifier = identifier(name, span)?;
+        let rust_type = match field_type {
+            FieldType::String => quote! { String },
+            FieldType::Option => quote! { Option<String> },
+            FieldType::Vec => quote! { Vec<String> },
+            FieldType::Data => quote! { Vec<#module #field_identifier> },
+        };
+        definitions.push(quote! { pub #field_identifier: #rust_type });
+        fields.insert(name.as_str(), (field_identifier, field_type));
+    }
+    let lines = template
+        .lines()
+        .iter()
+        .zip(template.line_endings().iter().copied())
+        .enumerate()
+        .map(|(index, (line, line_ending))| {
+            let previous = match index {
+                0 => LineEnding::Lf,
+                _ => template.line_endings()[index - 1],
+            };
+            render_line(line, line_ending, previous, &fields)
+        })
+        .collect::<Vec<_>>();
+    Ok(quote! {
+        #[allow(non_camel_case_types, non_snake_case)]
+        #[derive(Debug, Clone, Default, PartialEq, Eq)]
+        pub struct #struct_name {
+            #(#definitions,)*
+        }
+        #[allow(unused_mut, unused_variables, clippy::all)]
+        impl #struct_name {
+            pub fn render(&self) -> String {
+                self.render_with(&::drunk_snail::NoEscaper)
+            }
+            pub fn render_with(&self, escaper: &dyn ::drunk_snail::Escaper) -> String {
+                let mut result = String::new();
+                let mut pending = None;
+                self.render_lines(&mut result, escaper, "", "", &mut pending, None);
+                if let Some(line_ending) = pending {
+                    result.push_str(line_ending.as_str());
+                }
+                result
+            }
+            #[doc(hidden)]
+            pub fn render_lines(
+                &self,
+                result: &mut String,
+                escaper: &dyn ::drunk_snail::Escaper,
+                left: &str,
+                right: &str,
+                pending: &mut Option<::drunk_snail::LineEnding>,
+                separator: Option<::drunk_snail::LineEnding>,
+            ) {
+                #(#lines)*
+            }
+        }
+    })
+}
+
+fn expand(input: &Input) -> syn::Result<TokenStream> {
+    let syntax_value = |key: &str, default: &str| {
+        input
+            .syntax
+            .get(key)
+            .map_or(default.to_string(), LitStr::value)
+    };
+    let default_syntax = Syntax::default();
+    let (open_tag, close_tag, optional_operator, escape_operator, raw_operator, indent_operator) = (
+        syntax_value("open_tag", default_syntax.open_tag),
+        syntax_value("close_tag", default_syntax.close_tag),
+        syntax_value("optional_operator", default_syntax.optional_operator),
+        syntax_value("escape_operator", default_syntax.escape_operator),
+        syntax_value("raw_operator", default_syntax.raw_operator),
+        syntax_value("indent_operator", default_syntax.indent_operator),
+    );
+    let parser = Parser::from_syntax(
+        &Syntax {
+            open_tag: &open_tag,
+            close_tag: &close_tag,
+            optional_operator: &optional_operator,
+            escape_operator: &escape_operator,
+            raw_operator: &raw_operator,
+            indent_operator: &indent_operator,
+        },
+        &syntax_value("parameter_operator", "param"),
+        &syntax_value("reference_operator", "ref"),
+    )
+    .map_err(|error| Error::new(Span::call_site(), error.to_string()))?;
+    let sources = load(input, &parser)?;
+    let names: BTreeSet<&str> = sources
+        .iter()
+        .filter_map(|source| source.name.as_deref())
+        .collect();
+    if names.contains(input.name.to_string().as_str()) {
+        return Err(Error::new(
+            input.name.span(),
+            format!(
+                "\"{}\" is also the name of a referenced template",
+                input.name
+            ),
+        ));
+    }
+    // Referenced templates go into a module of their own, so that templates sharing a
+    // referenced one can be generated in the same module.
+    let module = identifier(&module_name(&input.name.to_string()), input.name.span())?;
+    let mut result = TokenStream::new();
+    let mut referenced = TokenStream::new();
+    for source in &sources {
+        let template = parser
+            .parse(&source.text)
+            .map_err(|error| Error::new(input.path.span(), error.to_string()))?;
+        let (struct_name, output, references) = match &source.name {
+            Some(name) => (
+                identifier(name, input.path.span())?,
+                &mut referenced,
+                TokenStream::new(),
+            ),
+            None => (input.name.clone(), &mut result, quote! { #module:: }),
+        };
+        output.extend(generate(
+            input,
+            &struct_name,
+            &template,
+            &names,
+            &references,
+        )?);
+        // Makes cargo rebuild when the template file changes.
+        let path = source.path.to_string_lossy();
+        output.extend(quote! { const _: &str = include_str!(#path); });
+    }
+    if !names.is_empty() {
+        let documentation = format!("Templates referenced by [`{}`].", input.name);
+        result.extend(quote! {
+            #[doc = #documentation]
+            pub mod #module {
+                #referenced
+            }
+        });
+    }
+    Ok(result)
+}
+
+/// `snake_case` of a `CamelCase` name, e.g. `menu_page` for `MenuPage`.
+fn module_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut previous = None;
+    for character in name.chars() {
+        if character.is_uppercase()
+            && previous
+                .is_some_and(|previous: char| previous.is_lowercase() || previous.is_ascii_digit())
+        {
+            result.push('_');
+        }
+        result.extend(character.to_lowercase());
+        previous = Some(character);
+    }
+    result
+}
+
+/// Generates a struct named by the first argument for the template file at the second one,
+/// plus a struct for every template it references, in a module named after the first
+/// argument in `snake_case`. Optional arguments: `lists = [name, ..]`
+/// for parameters that take several values, and `Syntax` fields and parser operators,
+/// e.g. `open_tag = "{{"`.
+#[proc_macro]
+pub fn template(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as Input);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implements `drunk_snail::TemplateData` with a parameter per field, named like the field
+/// unless renamed with `#[snail(rename = "...")]`. `Option` fields are left out when `None`,
+/// `Vec`s of scalars (`Vec<String>`, `Vec<&str>`, `Vec<i64>`, ..) become values Vecs and fields
+/// of other derived types nested parameters.
+#[proc_macro_derive(TemplateData, attributes(snail))]
+pub fn derive_template_data(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    data::expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,64 @@
+use drunk_snail::{Parser, RenderOptions, Syntax, TemplateData, Templates};
+use drunk_snail_macros::TemplateData;
+
+#[derive(TemplateData)]
+struct Table {
+    #[snail(rename = "Row")]
+    rows: Vec<Row>,
+    caption: Option<String>,
+    r#type: &'static str,
+    #[snail(rename = "class")]
+    classes: Vec<&'static str>,
+    #[snail(rename = "width")]
+    widths: Vec<i64>,
+}
+
+#[derive(TemplateData)]
+struct Row {
+    cell: Vec<String>,
+    #[snail(rename = "Tooltip")]
+    tooltip: Option<Tooltip>,
+}
+
+#[derive(TemplateData)]
+struct Tooltip(#[snail(rename = "text")] String);
+
+#[test]
+fn test_render_data() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([
+        (
+            "Row",
+            parser
+                .parse("<tr><!-- (param)cell --></tr>\n<!-- (optional)(ref)Tooltip -->")
+                .unwrap(),
+        ),
+        ("Tooltip", parser.parse("<!-- (param)text -->").unwrap()),
+    ]);
+    let template = parser
+        .parse("<table class=\"<!-- (param)type --> <!-- (param)class -->\">\n<col width=\"<!-- (param)width -->\">\n<!-- (ref)Row -->\n</table><!-- (optional)(param)caption -->")
+        .unwrap();
+    let table = Table {
+        rows: vec![
+            Row {
+                cell: vec!["1".to_string(), "2".to_string()],
+                tooltip: None,
+            },
+            Row {
+                cell: vec!["3".to_string()],
+                tooltip: Some(Tooltip("t".to_string())),
+            },
+        ],
+        caption: None,
+        r#type: "wide",
+        classes: vec!["striped"],
+        widths: vec![10, -1],
+    };
+    assert_eq!(table.names(), vec!["Row", "type", "class", "width"]);
+    assert_eq!(
+        template
+            .render_data(&table, &templates, &RenderOptions::default())
+            .unwrap(),
+        "<table class=\"wide striped\">\n<col width=\"10\">\n<col width=\"-1\">\n<tr>1</tr>\n<tr>2</tr>\n<tr>3</tr>\nt\n</table>"
+    );
+}
@@ -0,0 +1,234 @@
+use drunk_snail::{
+    HtmlEscaper, Parser, RenderOptions, Syntax, TemplateParameters, TemplateParametersValue,
+    Templates, params, tp_params, tp_params_vec, tp_value, tp_values,
+};
+
+drunk_snail_macros::template!(Table, "tests/templates/table.html", lists = [cell]);
+
+fn runtime_render(parameters: &TemplateParameters, options: &RenderOptions) -> String {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let table = include_str!("templates/table.html");
+    let row = include_str!("templates/Row.html");
+    parser
+        .parse(table)
+        .unwrap()
+        .render_with(
+            parameters,
+            &Templates::from([("Row", parser.parse(row).unwrap())]),
+            options,
+        )
+        .unwrap()
+}
+
+#[test]
+fn test_same_as_runtime() {
+    let table = Table {
+        Row: vec![
+            table::Row {
+                cell: vec!["1".to_string(), "<2>".to_string()],
+                note: Some("<b>n</b>".to_string()),
+            },
+            table::Row {
+                cell: vec!["3".to_string()],
+                note: None,
+            },
+        ],
+        caption: None,
+    };
+    let parameters = params! {
+        "Row" => tp_params_vec!(
+            params! {"cell" => tp_values!("1", "<2>"), "note" => tp_value!("<b>n</b>".to_string())},
+            params! {"cell" => tp_values!("3")}
+        )
+    };
+    assert_eq!(
+        table.render(),
+        runtime_render(&parameters, &RenderOptions::default())
+    );
+    let options = RenderOptions {
+        escaper: &HtmlEscaper,
+        ..RenderOptions::default()
+    };
+    assert_eq!(
+        table.render_with(&HtmlEscaper),
+        runtime_render(&parameters, &options)
+    );
+    assert_eq!(
+        Table {
+            caption: Some("c".to_string()),
+            ..table
+        }
+        .render(),
+        "<table>\n    <tr>\n        <td>1</td><td><b>n</b></td>\n        <td><2></td><td></td>\n    </tr>\n    <tr>\n        <td>3</td><td></td>\n    </tr>\n</table>\n<p>c</p>\n"
+    );
+}
+
+drunk_snail_macros::template!(Body, "tests/templates/body.html", lists = [cell]);
+
+#[test]
+fn test_shared_reference() {
+    // `Table` references `Row` too, each gets its own struct for it.
+    assert_eq!(
+        Body {
+            Row: vec![body::Row {
+                cell: vec!["1".to_string()],
+                note: None,
+            }],
+        }
+        .render(),
+        "<tbody>\n    <tr>\n        <td>1</td><td></td>\n    </tr>\n</tbody>\n"
+    );
+    assert_eq!(
+        Table {
+            Row: vec![table::Row {
+                cell: vec!["1".to_string()],
+                note: None,
+            }],
+            caption: None,
+        }
+        .render(),
+        "<table>\n    <tr>\n        <td>1</td><td></td>\n    </tr>\n</table>\n<p></p>\n"
+    );
+}
+
+drunk_snail_macros::template!(Echo, "tests/templates/echo.bat", lists = [line]);
+drunk_snail_macros::template!(Run, "tests/templates/run.bat", lists = [line]);
+
+#[test]
+fn test_line_endings_as_runtime() {
+    let echo = Echo {
+        line: vec!["a".to_string(), "b".to_string()],
+    };
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    assert_eq!(
+        echo.render(),
+        parser
+            .parse(include_str!("templates/echo.bat"))
+            .unwrap()
+            .render(&params! {"line" => tp_values!("a", "b")}, &Templates::new())
+            .unwrap()
+    );
+    assert_eq!(echo.render(), "@echo off\r\necho a\r\necho b");
+    let run = Run {
+        Command: vec![run::Command {
+            line: vec!["a".to_string(), "b".to_string()],
+        }],
+    };
+    assert_eq!(
+        run.render(),
+        parser
+            .parse(include_str!("templates/run.bat"))
+            .unwrap()
+            .render(
+                &params! {"Command" => tp_params! {"line" => tp_values!("a", "b")}},
+                &Templates::from([(
+                    "Command",
+                    parser.parse(include_str!("templates/Command.bat")).unwrap()
+                )])
+            )
+            .unwrap()
+    );
+    assert_eq!(run.render(), "echo a\r\necho b\r\nexit\r\n");
+}
+
+drunk_snail_macros::template!(Steps, "tests/templates/steps.yaml");
+
+#[test]
+fn test_indent_as_runtime() {
+    let steps = Steps {
+        Step: vec![
+            steps::Step {
+                run: "cargo build\ncargo test\n".to_string(),
+                name: "check".to_string(),
+            },
+            steps::Step {
+                run: "cargo doc".to_string(),
+                name: "doc".to_string(),
+            },
+        ],
+    };
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let parameters = params! {
+        "Step" => tp_params_vec!(
+            params! {"run" => tp_value!("cargo build\ncargo test\n"), "name" => tp_value!("check")},
+            params! {"run" => tp_value!("cargo doc"), "name" => tp_value!("doc")}
+        )
+    };
+    assert_eq!(
+        steps.render(),
+        parser
+            .parse(include_str!("templates/steps.yaml"))
+            .unwrap()
+            .render(
+                &parameters,
+                &Templates::from([(
+                    "Step",
+                    parser.parse(include_str!("templates/Step.yaml")).unwrap()
+                )])
+            )
+            .unwrap()
+    );
+    assert_eq!(
+        steps.render(),
+        "steps:\n  - run: |\n      cargo build # check\n      cargo test # check\n  - run: |\n      cargo doc # doc\n"
+    );
+}
+
+drunk_snail_macros::template!(Menu, "tests/templates/menu.html", lists = [label]);
+
+#[test]
+fn test_inline_references_as_runtime() {
+    let menu = Menu {
+        Icon: vec![menu::Icon {
+            name: "home".to_string(),
+        }],
+        label: vec!["Home".to_string(), "<Start>".to_string()],
+    };
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let parameters = params! {
+        "Icon" => tp_params! {"name" => tp_value!("home")},
+        "label" => tp_values!("Home", "<Start>")
+    };
+    let options = RenderOptions {
+        escaper: &HtmlEscaper,
+        ..RenderOptions::default()
+    };
+    assert_eq!(
+        menu.render_with(&HtmlEscaper),
+        parser
+            .parse(include_str!("templates/menu.html"))
+            .unwrap()
+            .render_with(
+                &parameters,
+                &Templates::from([(
+                    "Icon",
+                    parser.parse(include_str!("templates/Icon.html")).unwrap()
+                )]),
+                &options
+            )
+            .unwrap()
+    );
+    assert_eq!(
+        menu.render_with(&HtmlEscaper),
+        "<ul>\n<li><i class=\"home\"></i> Home</li>\n<li><i class=\"home\"></i> &lt;Start&gt;</li>\n</ul>\n"
+    );
+}
+
+drunk_snail_macros::template!(Nav, "tests/templates/nav.html");
+
+#[test]
+fn test_missing_inline_reference_as_runtime() {
+    let nav = Nav {
+        label: "Home".to_string(),
+    };
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    assert_eq!(
+        nav.render(),
+        parser
+            .parse(include_str!("templates/nav.html"))
+            .unwrap()
+            .render(&params! {"label" => tp_value!("Home")}, &Templates::new())
+            .unwrap()
+    );
+    assert_eq!(nav.render(), "<li>Home</li>\n");
+}
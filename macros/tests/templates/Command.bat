@@ -0,0 +1 @@
+echo <!-- (param)line -->
\ No newline at end of file
@@ -0,0 +1,2 @@
+@echo off
+echo <!-- (param)line -->
\ No newline at end of file
@@ -0,0 +1,2 @@
+<!-- (ref)Command -->
+exit
@@ -0,0 +1,160 @@
+//! Writing of lines that are assembled before being written: lines with `(indent)` values
+//! or inline references. Shared by the renderer and code generated by `drunk_snail_macros`.
+
+use crate::{Error, LineEnding, LineEndings, Sink};
+use std::ops::Range;
+
+/// Output of an inline reference split into lines.
+pub struct ReferenceLines {
+    output: String,
+    lines: Vec<(Range<usize>, LineEnding)>,
+}
+impl ReferenceLines {
+    pub fn new(output: String) -> Self {
+        let lines = LineEnding::split(&output)
+            .map(|(line, line_ending)| {
+                let start = line.as_ptr() as usize - output.as_ptr() as usize;
+                (start..start + line.len(), line_ending)
+            })
+            .collect();
+        ReferenceLines { output, lines }
+    }
+    /// Stands for an absent optional reference: a single empty line, so that the rest of the
+    /// line is still written.
+    pub fn empty() -> Self {
+        ReferenceLines {
+            output: String::new(),
+            lines: vec![(0..0, LineEnding::None)],
+        }
+    }
+    fn line(&self, index: usize) -> &str {
+        &self.output[self.lines[index].0.clone()]
+    }
+}
+
+pub fn push_line_ending<S: Sink + ?Sized>(
+    result: &mut S,
+    line_endings: LineEndings,
+    line_ending: LineEnding,
+) -> Result<(), Error> {
+    result.push_str(match line_endings {
+        LineEndings::Preserve => line_ending.as_str(),
+        LineEndings::Lf => "\n",
+        LineEndings::CrLf => "\r\n",
+    })
+}
+
+/// Terminates the previous line, line endings are written only once more output follows
+/// so that unterminated lines can still be separated when repeated, with `separator`.
+pub fn start_line<S: Sink + ?Sized>(
+    result: &mut S,
+    pending: &mut Option<LineEnding>,
+    line_endings: LineEndings,
+    separator: LineEnding,
+) -> Result<(), Error> {
+    match pending.take() {
+        Some(LineEnding::None) => push_line_ending(result, line_endings, separator),
+        Some(line_ending) => push_line_ending(result, line_endings, line_ending),
+        None => Ok(()),
+    }
+}
+
+/// Writes `line` with the text before and after each of `indents` repeated around every
+/// line of its value. A line break ending a value does not start another line.
+pub fn push_indented<S: Sink + ?Sized>(
+    result: &mut S,
+    line_endings: LineEndings,
+    line: &str,
+    indents: &[Range<usize>],
+) -> Result<(), Error> {
+    let mut position = 0;
+    for indent in indents {
+        result.push_str(&line[position..indent.start])?;
+        let mut value_lines = LineEnding::split(&line[indent.clone()]).peekable();
+        while let Some((value_line, value_line_ending)) = value_lines.next() {
+            result.push_str(value_line)?;
+            if value_lines.peek().is_some() {
+                result.push_str(&line[indent.end..])?;
+                push_line_ending(result, line_endings, value_line_ending)?;
+                result.push_str(&line[..indent.start])?;
+            }
+        }
+        position = indent.end;
+    }
+    result.push_str(&line[position..])
+}
+
+/// Writes `line` once per combination of the lines of `references`, last reference varying
+/// fastest, each inserted at its position. Nothing is written if a reference has no lines,
+/// e.g. for an empty parameters Vec.
+/// Combined lines end like the line of the last reference, or with `line_ending` when that
+/// one is unterminated, and are separated by `separator` when `line_ending` is unterminated.
+#[allow(clippy::too_many_arguments)]
+pub fn push_assembled_line<S: Sink + ?Sized>(
+    result: &mut S,
+    pending: &mut Option<LineEnding>,
+    line_endings: LineEndings,
+    line: &str,
+    positions: &[usize],
+    indents: &[Range<usize>],
+    references: &[ReferenceLines],
+    line_ending: LineEnding,
+    separator: LineEnding,
+) -> Result<(), Error> {
+    if references
+        .iter()
+        .any(|reference| reference.lines.is_empty())
+    {
+        return Ok(());
+    }
+    let mut current = vec![0; references.len()];
+    let mut spliced = String::new();
+    let mut spliced_indents = Vec::new();
+    loop {
+        start_line(result, pending, line_endings, separator)?;
+        spliced.clear();
+        let mut start = 0;
+        for (position, (reference, index)) in positions.iter().zip(references.iter().zip(&current))
+        {
+            spliced.push_str(&line[start..*position]);
+            spliced.push_str(reference.line(*index));
+            start = *position;
+        }
+        spliced.push_str(&line[start..]);
+        // References are never inside values, so values only move by the length of the
+        // reference lines inserted before them.
+        spliced_indents.clear();
+        spliced_indents.extend(indents.iter().map(|indent| {
+            let shift: usize = positions
+                .iter()
+                .zip(references.iter().zip(&current))
+                .filter(|(position, _)| **position <= indent.start)
+                .map(|(_, (reference, index))| reference.line(*index).len())
+                .sum();
+            indent.start + shift..indent.end + shift
+        }));
+        push_indented(result, line_endings, &spliced, &spliced_indents)?;
+        *pending = Some(
+            match references
+                .last()
+                .zip(current.last())
+                .map(|(reference, index)| reference.lines[*index].1)
+            {
+                None | Some(LineEnding::None) => line_ending,
+                Some(last_line_ending) => last_line_ending,
+            },
+        );
+        let mut index = current.len();
+        loop {
+            if index == 0 {
+                return Ok(());
+            }
+            index -= 1;
+            current[index] += 1;
+            if current[index] < references[index].lines.len() {
+                break;
+            }
+            current[index] = 0;
+        }
+    }
+}
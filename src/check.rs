@@ -0,0 +1,285 @@
+use crate::schema::template_schema;
+use crate::{
+    FieldKind, Location, Template, TemplateParameters, TemplateParametersValue, TemplateSchema,
+    Templates,
+};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Rendering would fail.
+    Error,
+    /// Rendering would succeed, but the data is probably not what was meant,
+    /// e.g. a missing non-optional reference key, an error only with
+    /// [`RenderOptions::strict_references`](crate::RenderOptions::strict_references).
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    MissingParameter,
+    ExpectedValue,
+    EmptyValuesVec,
+    ExpectedParameters,
+    MissingTemplate,
+    UnknownParameter,
+}
+
+/// Problem found by [`Template::check`] in template parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+    /// Path of the parameter in the data, e.g. `Row[3].cell`.
+    pub path: String,
+    /// Tag using the parameter, absent for unknown parameters.
+    pub location: Option<Location>,
+}
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = &self.path;
+        match self.kind {
+            IssueKind::MissingParameter => write!(f, "Expected key for parameter \"{path}\""),
+            IssueKind::ExpectedValue => write!(
+                f,
+                "Expected value or non-empty Vec of values for parameter \"{path}\""
+            ),
+            IssueKind::EmptyValuesVec => {
+                write!(
+                    f,
+                    "Expected non-empty Vec of values for parameter \"{path}\""
+                )
+            }
+            IssueKind::ExpectedParameters => write!(
+                f,
+                "Expected template parameters or template parameters Vec for template reference \"{path}\""
+            ),
+            IssueKind::MissingTemplate => {
+                write!(f, "No template provided for template reference \"{path}\"")
+            }
+            IssueKind::UnknownParameter => write!(f, "Parameter \"{path}\" is not used"),
+        }?;
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        Ok(())
+    }
+}
+
+struct Checker<'t> {
+    templates: &'t Templates<'t>,
+    schemas: HashMap<&'t str, TemplateSchema>,
+    issues: Vec<Issue>,
+}
+impl<'t> Checker<'t> {
+    fn issue(
+        &mut self,
+        severity: Severity,
+        kind: IssueKind,
+        path: String,
+        location: Option<&Location>,
+    ) {
+        self.issues.push(Issue {
+            severity,
+            kind,
+            path,
+            location: location.cloned(),
+        });
+    }
+    fn check(&mut self, schema: &TemplateSchema, parameters: &TemplateParameters, path: &str) {
+        let key_path = |name: &str| {
+            if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{path}.{name}")
+            }
+        };
+        for (name, field) in schema {
+            let Some(value) = parameters.get(name.as_str()) else {
+                if !field.is_optional {
+                    let severity = match field.kind {
+                        FieldKind::Value => Severity::Error,
+                        FieldKind::Reference => Severity::Warning,
+                    };
+                    self.issue(
+                        severity,
+                        IssueKind::MissingParameter,
+                        key_path(name),
+                        Some(&field.location),
+                    );
+                }
+                continue;
+            };
+            match (field.kind, value) {
+                (FieldKind::Value, TemplateParametersValue::Value(_)) => {}
+                (FieldKind::Value, TemplateParametersValue::ValuesVec(values)) => {
+                    if values.is_empty() {
+                        self.issue(
+                            Severity::Error,
+                            IssueKind::EmptyValuesVec,
+                            key_path(name),
+                            Some(&field.location),
+                        );
+                    }
+                }
+                (FieldKind::Value, TemplateParametersValue::ParametersVec(parameters_vec))
+                    if parameters_vec.is_empty() =>
+                {
+                    self.issue(
+                        Severity::Error,
+                        IssueKind::EmptyValuesVec,
+                        key_path(name),
+                        Some(&field.location),
+                    );
+                }
+                (FieldKind::Value, _) => {
+                    self.issue(
+                        Severity::Error,
+                        IssueKind::ExpectedValue,
+                        key_path(name),
+                        Some(&field.location),
+                    );
+                }
+                (
+                    FieldKind::Reference,
+                    TemplateParametersValue::Parameters(_)
+                    | TemplateParametersValue::ParametersVec(_),
+                ) => {
+                    let Some((&template_name, template)) =
+                        self.templates.get_key_value(name.as_str())
+                    else {
+                        if !field.is_optional {
+                            self.issue(
+                                Severity::Error,
+                                IssueKind::MissingTemplate,
+                                key_path(name),
+                                Some(&field.location),
+                            );
+                        }
+                        continue;
+                    };
+                    let subschema = self.schemas.remove(template_name).unwrap_or_else(|| {
+                        template_schema(template, &[template_name], &mut Vec::new())
+                    });
+                    match value {
+                        TemplateParametersValue::Parameters(subparameters) => {
+                            self.check(&subschema, subparameters, &key_path(name))
+                        }
+                        TemplateParametersValue::ParametersVec(subparameters_vec) => {
+                            for (index, subparameters) in subparameters_vec.iter().enumerate() {
+                                self.check(
+                                    &subschema,
+                                    subparameters,
+                                    &format!("{}[{index}]", key_path(name)),
+                                );
+                            }
+                        }
+                        _ => unreachable!("matched above"),
+                    }
+                    self.schemas.insert(template_name, subschema);
+                }
+                (FieldKind::Reference, _) => {
+                    self.issue(
+                        Severity::Error,
+                        IssueKind::ExpectedParameters,
+                        key_path(name),
+                        Some(&field.location),
+                    );
+                }
+            }
+        }
+        let mut unknown: Vec<&str> = parameters
+            .keys()
+            .filter(|name| !schema.contains_key(*name))
+            .collect();
+        unknown.sort_unstable();
+        for name in unknown {
+            self.issue(
+                Severity::Warning,
+                IssueKind::UnknownParameter,
+                key_path(name),
+                None,
+            );
+        }
+    }
+}
+
+impl Template<'_> {
+    /// Walks this template and `parameters` together, reporting every issue instead of
+    /// stopping at the first one like rendering does.
+    pub fn check(&self, parameters: &TemplateParameters, templates: &Templates) -> Vec<Issue> {
+        let mut checker = Checker {
+            templates,
+            schemas: HashMap::new(),
+            issues: Vec::new(),
+        };
+        checker.check(&template_schema(self, &[], &mut Vec::new()), parameters, "");
+        checker.issues
+    }
+}
+
+#[test]
+fn test_check() {
+    use crate::{params, tp_params, tp_params_vec, tp_value, tp_values};
+
+    let parser = crate::Parser::from_syntax(&crate::Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([(
+        "Row",
+        parser
+            .parse(
+                "<tr>\n  <td><!-- (param)cell --></td>\n  <!-- (optional)(ref)Tooltip -->\n</tr>",
+            )
+            .unwrap(),
+    )]);
+    let template = parser
+        .parse("<table>\n  <!-- (ref)Row -->\n</table><!-- (param)caption -->")
+        .unwrap();
+    let issues = template.check(
+        &params! {
+            "Row" => tp_params_vec!(
+                params! {"cell" => tp_values!("1")},
+                params! {"cell" => tp_values!()},
+                params! {"cell" => tp_params! {}, "Tooltip" => tp_params! {}},
+                params! {"Cell" => tp_value!("4".to_string())},
+                params! {"cell" => tp_params_vec!()}
+            ),
+            "Rows" => tp_params! {}
+        },
+        &templates,
+    );
+    assert_eq!(
+        issues
+            .iter()
+            .map(|issue| (issue.severity, issue.kind, issue.path.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            (Severity::Error, IssueKind::EmptyValuesVec, "Row[1].cell"),
+            (Severity::Error, IssueKind::ExpectedValue, "Row[2].cell"),
+            (Severity::Error, IssueKind::MissingParameter, "Row[3].cell"),
+            (
+                Severity::Warning,
+                IssueKind::UnknownParameter,
+                "Row[3].Cell"
+            ),
+            (Severity::Error, IssueKind::EmptyValuesVec, "Row[4].cell"),
+            (Severity::Error, IssueKind::MissingParameter, "caption"),
+            (Severity::Warning, IssueKind::UnknownParameter, "Rows"),
+        ]
+    );
+    assert_eq!(
+        template
+            .check(
+                &params! {"caption" => tp_value!("c".to_string())},
+                &templates
+            )
+            .iter()
+            .map(|issue| (issue.severity, issue.kind, issue.path.as_str()))
+            .collect::<Vec<_>>(),
+        vec![(Severity::Warning, IssueKind::MissingParameter, "Row")]
+    );
+    assert_eq!(
+        issues[0].to_string(),
+        "Expected non-empty Vec of values for parameter \"Row[1].cell\" at template \"Row\", line 2, column 7"
+    );
+}
@@ -0,0 +1,234 @@
+//! Data rendering reads parameters from: [`TemplateParameters`], user types deriving
+//! `TemplateData` with `drunk_snail_macros`, or anything else implementing [`TemplateData`].
+
+use crate::{
+    Error, RenderOptions, Scalar, Template, TemplateParameters, TemplateParametersValue, Templates,
+};
+use std::borrow::Cow;
+use std::ops::Deref;
+
+/// Parameter as seen by the renderer.
+pub enum ValueRef<'v> {
+    /// For `(param)` tags.
+    Value(Scalar<'v>),
+    /// For `(param)` tags, the line is repeated once per value.
+    Values(Box<dyn Iterator<Item = Scalar<'v>> + 'v>),
+    /// For `(ref)` tags.
+    Data(&'v dyn TemplateData),
+    /// For `(ref)` tags, the referenced template is rendered once per item.
+    DataIter(Box<dyn Iterator<Item = DataRef<'v>> + 'v>),
+}
+
+/// Item of [`ValueRef::DataIter`], owned when produced on the fly, e.g. by [`LazyData`](crate::LazyData).
+pub enum DataRef<'v> {
+    Borrowed(&'v dyn TemplateData),
+    Owned(Box<dyn TemplateData + 'v>),
+}
+impl<'v> Deref for DataRef<'v> {
+    type Target = dyn TemplateData + 'v;
+    fn deref(&self) -> &Self::Target {
+        match self {
+            DataRef::Borrowed(data) => *data,
+            DataRef::Owned(data) => data.as_ref(),
+        }
+    }
+}
+
+/// Parameters of a template.
+pub trait TemplateData {
+    fn get(&self, name: &str) -> Option<ValueRef<'_>>;
+    /// Names of present parameters, used to report unused ones.
+    fn names(&self) -> Vec<&str>;
+}
+
+impl TemplateData for TemplateParameters<'_> {
+    fn get(&self, name: &str) -> Option<ValueRef<'_>> {
+        Some(match TemplateParameters::get(self, name)? {
+            TemplateParametersValue::Value(value) => ValueRef::Value(value.reborrow()),
+            TemplateParametersValue::ValuesVec(values) => {
+                ValueRef::Values(Box::new(values.iter().map(Scalar::reborrow)))
+            }
+            TemplateParametersValue::Parameters(parameters) => ValueRef::Data(parameters),
+            TemplateParametersValue::ParametersVec(parameters_vec) => ValueRef::DataIter(Box::new(
+                parameters_vec
+                    .iter()
+                    .map(|parameters| DataRef::Borrowed(parameters)),
+            )),
+        })
+    }
+    fn names(&self) -> Vec<&str> {
+        self.keys().collect()
+    }
+}
+
+/// Type of a [`TemplateData`] field, implemented by `#[derive(TemplateData)]` for nested data.
+/// `None` leaves the parameter out, which satisfies optional tags.
+pub trait TemplateValue {
+    fn to_value_ref(&self) -> Option<ValueRef<'_>>;
+    /// Whether [`TemplateValue::to_value_ref`] would return something, without consuming lazy values.
+    fn is_present(&self) -> bool {
+        self.to_value_ref().is_some()
+    }
+}
+
+impl TemplateValue for str {
+    fn to_value_ref(&self) -> Option<ValueRef<'_>> {
+        Some(ValueRef::Value(Scalar::from(self)))
+    }
+}
+impl TemplateValue for String {
+    fn to_value_ref(&self) -> Option<ValueRef<'_>> {
+        self.as_str().to_value_ref()
+    }
+}
+impl TemplateValue for Cow<'_, str> {
+    fn to_value_ref(&self) -> Option<ValueRef<'_>> {
+        self.as_ref().to_value_ref()
+    }
+}
+impl TemplateValue for Scalar<'_> {
+    fn to_value_ref(&self) -> Option<ValueRef<'_>> {
+        Some(ValueRef::Value(self.reborrow()))
+    }
+}
+
+macro_rules! impl_template_value_for_copy {
+    ($($source:ty),*) => {
+        $(
+            impl TemplateValue for $source {
+                fn to_value_ref(&self) -> Option<ValueRef<'_>> {
+                    Some(ValueRef::Value(Scalar::from(*self)))
+                }
+            }
+        )*
+    };
+}
+impl_template_value_for_copy!(
+    bool, char, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64
+);
+
+impl<T: TemplateValue + ?Sized> TemplateValue for &T {
+    fn to_value_ref(&self) -> Option<ValueRef<'_>> {
+        (**self).to_value_ref()
+    }
+    fn is_present(&self) -> bool {
+        (**self).is_present()
+    }
+}
+impl<T: TemplateValue> TemplateValue for Option<T> {
+    fn to_value_ref(&self) -> Option<ValueRef<'_>> {
+        self.as_ref()?.to_value_ref()
+    }
+    fn is_present(&self) -> bool {
+        self.as_ref().is_some_and(TemplateValue::is_present)
+    }
+}
+impl<T: TemplateVecItem> TemplateValue for Vec<T> {
+    fn to_value_ref(&self) -> Option<ValueRef<'_>> {
+        Some(T::to_values_ref(self))
+    }
+}
+
+/// Item type of a `Vec` field: scalars make values Vecs for `(param)` tags, [`TemplateData`]
+/// types parameters Vecs for `(ref)` tags.
+pub trait TemplateVecItem: Sized {
+    fn to_values_ref(items: &[Self]) -> ValueRef<'_>;
+}
+impl<D: TemplateData> TemplateVecItem for D {
+    fn to_values_ref(items: &[Self]) -> ValueRef<'_> {
+        ValueRef::DataIter(Box::new(items.iter().map(|data| DataRef::Borrowed(data))))
+    }
+}
+
+macro_rules! impl_template_vec_item_for_scalar {
+    ($($item:ty => |$value:ident| $scalar:expr),* $(,)?) => {
+        $(
+            impl TemplateVecItem for $item {
+                fn to_values_ref(items: &[Self]) -> ValueRef<'_> {
+                    ValueRef::Values(Box::new(items.iter().map(|$value| $scalar)))
+                }
+            }
+        )*
+    };
+}
+impl_template_vec_item_for_scalar!(
+    String => |value| Scalar::from(value),
+    &str => |value| Scalar::from(*value),
+    Cow<'_, str> => |value| Scalar::from(value.as_ref()),
+    Scalar<'_> => |value| value.reborrow(),
+);
+macro_rules! impl_template_vec_item_for_copy {
+    ($($item:ty),*) => {
+        impl_template_vec_item_for_scalar!($($item => |value| Scalar::from(*value)),*);
+    };
+}
+impl_template_vec_item_for_copy!(
+    bool, char, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64
+);
+
+impl Template<'_> {
+    /// Same as [`Template::render_with`], named for rendering user types.
+    pub fn render_data(
+        &self,
+        data: &dyn TemplateData,
+        templates: &Templates,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
+        self.render_with(data, templates, options)
+    }
+}
+
+#[test]
+fn test_custom_template_data() {
+    struct Row<'r> {
+        cells: &'r [&'r str],
+    }
+    impl TemplateData for Row<'_> {
+        fn get(&self, name: &str) -> Option<ValueRef<'_>> {
+            match name {
+                "cell" => Some(ValueRef::Values(Box::new(
+                    self.cells.iter().map(|cell| Scalar::from(*cell)),
+                ))),
+                _ => None,
+            }
+        }
+        fn names(&self) -> Vec<&str> {
+            vec!["cell"]
+        }
+    }
+    struct Table<'r> {
+        rows: Vec<Row<'r>>,
+    }
+    impl TemplateData for Table<'_> {
+        fn get(&self, name: &str) -> Option<ValueRef<'_>> {
+            match name {
+                "Row" => Some(ValueRef::DataIter(Box::new(
+                    self.rows.iter().map(|row| DataRef::Borrowed(row)),
+                ))),
+                "caption" => Some(ValueRef::Value(Scalar::from(self.rows.len()))),
+                _ => None,
+            }
+        }
+        fn names(&self) -> Vec<&str> {
+            vec!["Row", "caption"]
+        }
+    }
+
+    let parser = crate::Parser::from_syntax(&crate::Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([(
+        "Row",
+        parser.parse("<tr><!-- (param)cell --></tr>").unwrap(),
+    )]);
+    let template = parser
+        .parse("<table>\n<!-- (ref)Row -->\n</table><!-- (param)caption -->")
+        .unwrap();
+    let table = Table {
+        rows: vec![Row { cells: &["1", "2"] }, Row { cells: &["3"] }],
+    };
+    assert_eq!(
+        template
+            .render_data(&table, &templates, &RenderOptions::default())
+            .unwrap(),
+        "<table>\n<tr>1</tr>\n<tr>2</tr>\n<tr>3</tr>\n</table>2"
+    );
+}
@@ -0,0 +1,287 @@
+use crate::{Error, Parser, Template, Templates};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How [`TemplateDirectory::load`] names templates after their files. Reference tags accept
+/// all of these names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TemplateNames {
+    /// File name without extension, `rows/Row.html` is `Row`.
+    #[default]
+    FileStem,
+    /// Path relative to the directory without extension, joined by dots: `rows/Row.html` is
+    /// `rows.Row`.
+    DottedPath,
+    /// Path relative to the directory without extension, joined by slashes: `rows/Row.html`
+    /// is `rows/Row`.
+    SlashedPath,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DirectoryOptions<'a> {
+    /// Extensions of template files without the dot, every file is a template if empty.
+    pub extensions: &'a [&'a str],
+    pub names: TemplateNames,
+    /// Parsers for files with the given extension, other files use the default parser.
+    pub parsers: &'a [(&'a str, &'a Parser)],
+}
+
+/// Templates read from a directory tree, by name.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateDirectory {
+    templates: BTreeMap<String, (PathBuf, Template<'static>)>,
+}
+impl TemplateDirectory {
+    /// Reads and parses every template file under `directory`, subdirectories included.
+    /// Fails on unreadable files and on files that get the same name.
+    pub fn load(
+        directory: impl AsRef<Path>,
+        parser: &Parser,
+        options: &DirectoryOptions,
+    ) -> Result<Self, Error> {
+        let directory = directory.as_ref();
+        let mut files = Vec::new();
+        collect_files(directory, options.extensions, &mut files)?;
+        let mut templates = BTreeMap::new();
+        for path in files {
+            let name = template_name(directory, &path, options.names)?;
+            if let Some((first, _)) = templates.get(&name) {
+                return Err(Error::DuplicateTemplate {
+                    name,
+                    first: PathBuf::clone(first),
+                    second: path,
+                });
+            }
+            let source = fs::read_to_string(&path).map_err(|error| Error::ReadTemplate {
+                path: path.clone(),
+                error,
+            })?;
+            let parser = extension(&path)
+                .and_then(|extension| {
+                    options
+                        .parsers
+                        .iter()
+                        .find(|(parser_extension, _)| *parser_extension == extension)
+                })
+                .map_or(parser, |(_, parser)| *parser);
+            let template = parser.parse_owned(source)?;
+            templates.insert(name, (path, template));
+        }
+        Ok(TemplateDirectory { templates })
+    }
+    pub fn get(&self, name: &str) -> Option<&Template<'static>> {
+        self.templates.get(name).map(|(_, template)| template)
+    }
+    /// File template `name` was read from.
+    pub fn path(&self, name: &str) -> Option<&Path> {
+        self.templates.get(name).map(|(path, _)| path.as_path())
+    }
+    /// Names in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.templates.keys().map(String::as_str)
+    }
+    /// All templates, for rendering templates from the directory or any other. Templates are
+    /// shared rather than copied, still the result is meant to be kept for several renders.
+    pub fn templates(&self) -> Templates<'_> {
+        self.templates
+            .iter()
+            .map(|(name, (_, template))| (name.as_str(), template.clone()))
+            .collect()
+    }
+}
+
+fn extension(path: &Path) -> Option<&str> {
+    path.extension().and_then(|extension| extension.to_str())
+}
+
+fn collect_files(
+    directory: &Path,
+    extensions: &[&str],
+    files: &mut Vec<PathBuf>,
+) -> Result<(), Error> {
+    let read_error = |error| Error::ReadTemplate {
+        path: directory.to_path_buf(),
+        error,
+    };
+    let mut paths = fs::read_dir(directory)
+        .map_err(read_error)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_error)?;
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            collect_files(&path, extensions, files)?;
+        } else if path.is_file()
+            && (extensions.is_empty()
+                || extension(&path).is_some_and(|extension| extensions.contains(&extension)))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn template_name(directory: &Path, path: &Path, names: TemplateNames) -> Result<String, Error> {
+    let name = match names {
+        TemplateNames::FileStem => path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(str::to_string),
+        TemplateNames::DottedPath | TemplateNames::SlashedPath => {
+            let separator = match names {
+                TemplateNames::DottedPath => ".",
+                _ => "/",
+            };
+            let relative = path
+                .strip_prefix(directory)
+                .expect("files are collected from inside the directory")
+                .with_extension("");
+            relative
+                .iter()
+                .map(|component| component.to_str())
+                .collect::<Option<Vec<_>>>()
+                .map(|components| components.join(separator))
+        }
+    };
+    name.ok_or_else(|| Error::TemplateName {
+        path: path.to_path_buf(),
+    })
+}
+
+#[test]
+fn test_load() {
+    use crate::{Syntax, TemplateParametersValue, params, tp_params_vec, tp_value};
+
+    let directory =
+        std::env::temp_dir().join(format!("drunk_snail_test_load_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(directory.join("rows")).unwrap();
+    fs::write(
+        directory.join("Table.html"),
+        "<table>\n<!-- (ref)Row -->\n</table>",
+    )
+    .unwrap();
+    fs::write(
+        directory.join("rows/Row.html"),
+        "<tr><!-- (param)cell --></tr>\n",
+    )
+    .unwrap();
+    fs::write(
+        directory.join("rows/Query.sql"),
+        "select {{ (param)column }}",
+    )
+    .unwrap();
+    fs::write(directory.join("notes.txt"), "not a template").unwrap();
+
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let sql_parser = Parser::from_syntax(
+        &Syntax {
+            open_tag: "{{",
+            close_tag: "}}",
+            ..Syntax::default()
+        },
+        "param",
+        "ref",
+    )
+    .unwrap();
+
+    let loaded = TemplateDirectory::load(
+        &directory,
+        &parser,
+        &DirectoryOptions {
+            extensions: &["html", "sql"],
+            parsers: &[("sql", &sql_parser)],
+            ..DirectoryOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        loaded.names().collect::<Vec<_>>(),
+        vec!["Query", "Row", "Table"]
+    );
+    assert_eq!(
+        loaded.path("Row"),
+        Some(directory.join("rows/Row.html").as_path())
+    );
+    let templates = loaded.templates();
+    assert_eq!(
+        loaded
+            .get("Table")
+            .unwrap()
+            .render(
+                &params! {"Row" => tp_params_vec!(params! {"cell" => tp_value!("1".to_string())}, params! {"cell" => tp_value!("2".to_string())})},
+                &templates
+            )
+            .unwrap(),
+        "<table>\n<tr>1</tr>\n<tr>2</tr>\n</table>"
+    );
+    assert_eq!(
+        loaded
+            .get("Query")
+            .unwrap()
+            .render(
+                &params! {"column" => tp_value!("id".to_string())},
+                &templates
+            )
+            .unwrap(),
+        "select id"
+    );
+
+    let loaded = TemplateDirectory::load(
+        &directory,
+        &parser,
+        &DirectoryOptions {
+            names: TemplateNames::SlashedPath,
+            ..DirectoryOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        loaded.names().collect::<Vec<_>>(),
+        vec!["Table", "notes", "rows/Query", "rows/Row"]
+    );
+
+    fs::write(directory.join("List.html"), "<!-- (ref)rows.Row -->\n").unwrap();
+    let loaded = TemplateDirectory::load(
+        &directory,
+        &parser,
+        &DirectoryOptions {
+            extensions: &["html"],
+            names: TemplateNames::DottedPath,
+            ..DirectoryOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        loaded.names().collect::<Vec<_>>(),
+        vec!["List", "Table", "rows.Row"]
+    );
+    assert_eq!(
+        loaded
+            .get("List")
+            .unwrap()
+            .render(
+                &params! {"rows.Row" => tp_params_vec!(params! {"cell" => tp_value!("1".to_string())})},
+                &loaded.templates()
+            )
+            .unwrap(),
+        "<tr>1</tr>\n"
+    );
+
+    fs::write(directory.join("Row.txt"), "duplicate").unwrap();
+    assert!(matches!(
+        TemplateDirectory::load(&directory, &parser, &DirectoryOptions::default()),
+        Err(Error::DuplicateTemplate { name, first, second })
+            if name == "Row"
+                && first == directory.join("Row.txt")
+                && second == directory.join("rows/Row.html")
+    ));
+    assert!(matches!(
+        TemplateDirectory::load(directory.join("missing"), &parser, &DirectoryOptions::default()),
+        Err(Error::ReadTemplate { path, .. }) if path == directory.join("missing")
+    ));
+
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,133 @@
+use std::borrow::Cow;
+
+/// Transforms parameter values before they are written to the output.
+///
+/// Selected per render call with [`RenderOptions::escaper`](crate::RenderOptions::escaper);
+/// parameters tagged with the raw operator, e.g. `<!-- (raw)(param)x -->`, bypass it.
+pub trait Escaper {
+    fn escape<'v>(&self, value: &'v str) -> Cow<'v, str>;
+    /// Lets values other than strings be formatted straight into the output.
+    fn escapes_nothing(&self) -> bool {
+        false
+    }
+}
+
+fn replace_chars<'v>(
+    value: &'v str,
+    replacement: impl Fn(char) -> Option<&'static str>,
+) -> Cow<'v, str> {
+    let Some(first) = value.find(|character| replacement(character).is_some()) else {
+        return Cow::Borrowed(value);
+    };
+    let mut result = String::with_capacity(value.len() + 8);
+    result.push_str(&value[..first]);
+    for character in value[first..].chars() {
+        match replacement(character) {
+            Some(replaced) => result.push_str(replaced),
+            None => result.push(character),
+        }
+    }
+    Cow::Owned(result)
+}
+
+/// Leaves values untouched.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoEscaper;
+impl Escaper for NoEscaper {
+    fn escape<'v>(&self, value: &'v str) -> Cow<'v, str> {
+        Cow::Borrowed(value)
+    }
+    fn escapes_nothing(&self) -> bool {
+        true
+    }
+}
+
+/// Escapes `&`, `<`, `>`, `"` and `'` for HTML text and quoted attribute values.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HtmlEscaper;
+impl Escaper for HtmlEscaper {
+    fn escape<'v>(&self, value: &'v str) -> Cow<'v, str> {
+        replace_chars(value, |character| match character {
+            '&' => Some("&amp;"),
+            '<' => Some("&lt;"),
+            '>' => Some("&gt;"),
+            '"' => Some("&quot;"),
+            '\'' => Some("&#x27;"),
+            _ => None,
+        })
+    }
+}
+
+/// Escapes values for quoted XML attributes, keeping whitespace from being normalized away.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct XmlAttributeEscaper;
+impl Escaper for XmlAttributeEscaper {
+    fn escape<'v>(&self, value: &'v str) -> Cow<'v, str> {
+        replace_chars(value, |character| match character {
+            '&' => Some("&amp;"),
+            '<' => Some("&lt;"),
+            '>' => Some("&gt;"),
+            '"' => Some("&quot;"),
+            '\'' => Some("&apos;"),
+            '\t' => Some("&#9;"),
+            '\n' => Some("&#10;"),
+            '\r' => Some("&#13;"),
+            _ => None,
+        })
+    }
+}
+
+/// Escapes values for the inside of a JSON string literal, the quotes come from the template.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonStringEscaper;
+impl Escaper for JsonStringEscaper {
+    fn escape<'v>(&self, value: &'v str) -> Cow<'v, str> {
+        const CONTROL: [&str; 32] = [
+            "\\u0000", "\\u0001", "\\u0002", "\\u0003", "\\u0004", "\\u0005", "\\u0006", "\\u0007",
+            "\\b", "\\t", "\\n", "\\u000b", "\\f", "\\r", "\\u000e", "\\u000f", "\\u0010",
+            "\\u0011", "\\u0012", "\\u0013", "\\u0014", "\\u0015", "\\u0016", "\\u0017", "\\u0018",
+            "\\u0019", "\\u001a", "\\u001b", "\\u001c", "\\u001d", "\\u001e", "\\u001f",
+        ];
+        replace_chars(value, |character| match character {
+            '"' => Some("\\\""),
+            '\\' => Some("\\\\"),
+            '\u{0}'..='\u{1f}' => Some(CONTROL[character as usize]),
+            _ => None,
+        })
+    }
+}
+
+/// Quotes values as a single POSIX shell word.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ShellEscaper;
+impl Escaper for ShellEscaper {
+    fn escape<'v>(&self, value: &'v str) -> Cow<'v, str> {
+        let mut result = String::with_capacity(value.len() + 2);
+        result.push('\'');
+        result.push_str(&value.replace('\'', "'\\''"));
+        result.push('\'');
+        Cow::Owned(result)
+    }
+}
+
+#[test]
+fn test_escapers() {
+    assert!(matches!(
+        HtmlEscaper.escape("plain text"),
+        Cow::Borrowed("plain text")
+    ));
+    assert_eq!(
+        HtmlEscaper.escape("<a href=\"x\">Tom & 'Jerry'</a>"),
+        "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#x27;Jerry&#x27;&lt;/a&gt;"
+    );
+    assert_eq!(
+        XmlAttributeEscaper.escape("a\t'b'\n<c>"),
+        "a&#9;&apos;b&apos;&#10;&lt;c&gt;"
+    );
+    assert_eq!(
+        JsonStringEscaper.escape("say \"hi\"\\\n\u{1}"),
+        "say \\\"hi\\\"\\\\\\n\\u0001"
+    );
+    assert_eq!(ShellEscaper.escape("it's $HOME"), "'it'\\''s $HOME'");
+    assert_eq!(ShellEscaper.escape(""), "''");
+}
@@ -0,0 +1,259 @@
+//! Conversion of `serde_json::Value` and `Serialize` types into [`TemplateParameters`].
+//!
+//! Objects become [`TemplateParametersValue::Parameters`], arrays of scalars
+//! [`TemplateParametersValue::ValuesVec`], arrays of objects (including empty arrays)
+//! [`TemplateParametersValue::ParametersVec`] and scalars [`TemplateParametersValue::Value`].
+//! `null` object members are skipped, so they satisfy optional tags.
+
+use crate::{
+    Error, RenderOptions, Scalar, Template, TemplateParameters, TemplateParametersValue, Templates,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+/// Reason a JSON value can not be represented as template parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonMismatch {
+    RootNotObject,
+    MixedArray,
+    NestedArray,
+    NullInArray,
+    /// `null` converted on its own, it is only meaningful as a skipped object member.
+    Null,
+}
+impl fmt::Display for JsonMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JsonMismatch::RootNotObject => "expected object at the top level",
+            JsonMismatch::MixedArray => "array mixes objects and scalars",
+            JsonMismatch::NestedArray => "arrays of arrays are not supported",
+            JsonMismatch::NullInArray => "arrays can not contain null",
+            JsonMismatch::Null => "expected non-null value",
+        })
+    }
+}
+
+fn scalar(value: &Value) -> Option<Scalar<'_>> {
+    match value {
+        Value::String(value) => Some(Scalar::from(value)),
+        Value::Number(value) => Some(if let Some(value) = value.as_i64() {
+            Scalar::Int(value)
+        } else if let Some(value) = value.as_u64() {
+            Scalar::UInt(value)
+        } else {
+            // Keeps serde_json formatting of floats, e.g. `1.0`.
+            Scalar::display(value)
+        }),
+        Value::Bool(value) => Some(Scalar::Bool(*value)),
+        _ => None,
+    }
+}
+
+fn mismatch(path: &str, mismatch: JsonMismatch) -> Error {
+    Error::Json {
+        path: path.to_string(),
+        mismatch,
+    }
+}
+
+fn parameters<'a>(
+    object: &'a serde_json::Map<String, Value>,
+    path: &str,
+) -> Result<TemplateParameters<'a>, Error> {
+    let mut result = TemplateParameters::with_capacity(object.len());
+    for (key, value) in object {
+        let key_path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}.{key}")
+        };
+        if let Some(value) = parameters_value(value, &key_path)? {
+            result.insert(key.as_str(), value);
+        }
+    }
+    Ok(result)
+}
+
+fn parameters_value<'a>(
+    value: &'a Value,
+    path: &str,
+) -> Result<Option<TemplateParametersValue<'a>>, Error> {
+    Ok(Some(match value {
+        Value::Null => return Ok(None),
+        Value::Object(object) => TemplateParametersValue::Parameters(parameters(object, path)?),
+        Value::Array(array) => {
+            if array.iter().all(Value::is_object) {
+                TemplateParametersValue::ParametersVec(
+                    array
+                        .iter()
+                        .enumerate()
+                        .map(|(index, element)| match element {
+                            Value::Object(object) => {
+                                parameters(object, &format!("{path}[{index}]"))
+                            }
+                            _ => unreachable!("all elements are objects"),
+                        })
+                        .collect::<Result<_, _>>()?,
+                )
+            } else {
+                TemplateParametersValue::ValuesVec(
+                    array
+                        .iter()
+                        .enumerate()
+                        .map(|(index, element)| {
+                            scalar(element).ok_or_else(|| {
+                                mismatch(
+                                    &format!("{path}[{index}]"),
+                                    match element {
+                                        Value::Null => JsonMismatch::NullInArray,
+                                        Value::Array(_) => JsonMismatch::NestedArray,
+                                        _ => JsonMismatch::MixedArray,
+                                    },
+                                )
+                            })
+                        })
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+        }
+        scalar_value => TemplateParametersValue::Value(
+            scalar(scalar_value).expect("remaining JSON values are scalars"),
+        ),
+    }))
+}
+
+/// Borrows keys from `value`, which must be a JSON object.
+pub fn parameters_from_json(value: &Value) -> Result<TemplateParameters<'_>, Error> {
+    match value {
+        Value::Object(object) => parameters(object, ""),
+        _ => Err(mismatch("", JsonMismatch::RootNotObject)),
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for TemplateParametersValue<'a> {
+    type Error = Error;
+    /// Converts a non-null value the same way [`parameters_from_json`] converts object members.
+    fn try_from(value: &'a Value) -> Result<Self, Error> {
+        parameters_value(value, "")?.ok_or_else(|| mismatch("", JsonMismatch::Null))
+    }
+}
+
+impl Template<'_> {
+    pub fn render_json(
+        &self,
+        data: &Value,
+        templates: &Templates,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
+        self.render_with(&parameters_from_json(data)?, templates, options)
+    }
+    /// Serializes `data` into a `serde_json::Value` first, then renders it like [`Template::render_json`].
+    /// Both the `Value` and the [`TemplateParameters`] tree borrowing it are allocated in full, so
+    /// types deriving [`TemplateData`](crate::TemplateData) render cheaper with [`Template::render_with`].
+    pub fn render_serialize(
+        &self,
+        data: &impl Serialize,
+        templates: &Templates,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
+        let value = serde_json::to_value(data).map_err(Error::Serialize)?;
+        self.render_with(&parameters_from_json(&value)?, templates, options)
+    }
+}
+
+#[test]
+fn test_render_json() {
+    let parser = crate::Parser::from_syntax(&crate::Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([(
+        "Row",
+        parser
+            .parse("<tr>\n  <td><!-- (param)cell --></td>\n</tr>")
+            .unwrap(),
+    )]);
+    let table = parser
+        .parse("<table>\n  <!-- (ref)Row -->\n</table><!-- (optional)(param)caption -->")
+        .unwrap();
+    let data = serde_json::json!({
+        "Row": [{"cell": ["1", "2"]}, {"cell": [3, true]}],
+        "caption": null
+    });
+    assert_eq!(
+        table
+            .render_json(&data, &templates, &RenderOptions::default())
+            .unwrap(),
+        "<table>\n  <tr>\n    <td>1</td>\n    <td>2</td>\n  </tr>\n  <tr>\n    <td>3</td>\n    <td>true</td>\n  </tr>\n</table>"
+    );
+
+    #[derive(Serialize)]
+    struct Row {
+        cell: Vec<u32>,
+    }
+    #[derive(Serialize)]
+    struct Table {
+        #[serde(rename = "Row")]
+        rows: Vec<Row>,
+    }
+    assert_eq!(
+        table
+            .render_serialize(
+                &Table {
+                    rows: vec![Row { cell: vec![1] }]
+                },
+                &templates,
+                &RenderOptions::default()
+            )
+            .unwrap(),
+        "<table>\n  <tr>\n    <td>1</td>\n  </tr>\n</table>"
+    );
+
+    assert_eq!(
+        table
+            .render_json(
+                &serde_json::json!({"Row": []}),
+                &templates,
+                &RenderOptions::default()
+            )
+            .unwrap(),
+        "<table>\n</table>"
+    );
+    assert!(matches!(
+        table.render_json(
+            &serde_json::json!({"Row": [{"cell": []}]}),
+            &templates,
+            &RenderOptions::default()
+        ),
+        Err(Error::EmptyValuesVec { name, .. }) if name == "cell"
+    ));
+}
+
+#[test]
+fn test_json_mismatch() {
+    let error = |data: Value| match parameters_from_json(&data) {
+        Err(Error::Json { path, mismatch }) => (path, mismatch),
+        _ => panic!("expected JSON mismatch for {data}"),
+    };
+    assert_eq!(
+        error(serde_json::json!(["a"])),
+        (String::new(), JsonMismatch::RootNotObject)
+    );
+    assert_eq!(
+        error(serde_json::json!({"Row": [{"cell": ["a", {"b": "c"}]}]})),
+        ("Row[0].cell[1]".to_string(), JsonMismatch::MixedArray)
+    );
+    assert_eq!(
+        error(serde_json::json!({"cell": [["a"]]})),
+        ("cell[0]".to_string(), JsonMismatch::NestedArray)
+    );
+    assert_eq!(
+        error(serde_json::json!({"cell": ["a", null]})),
+        ("cell[1]".to_string(), JsonMismatch::NullInArray)
+    );
+    assert!(matches!(
+        TemplateParametersValue::try_from(&Value::Null),
+        Err(Error::Json {
+            mismatch: JsonMismatch::Null,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,142 @@
+//! Values produced by iterators while rendering, so large datasets are never held in memory.
+//! Combined with [`Template::render_to_writer`](crate::Template::render_to_writer) the whole
+//! render runs in constant memory.
+//!
+//! Each is consumed by the first tag reading it: later tags with the same name see it as absent.
+
+use crate::{DataRef, Scalar, TemplateData, TemplateValue, ValueRef};
+use std::cell::RefCell;
+
+/// Values for a `(param)` tag, like [`TemplateParametersValue::ValuesVec`](crate::TemplateParametersValue::ValuesVec).
+pub struct LazyValues<'i, V> {
+    values: RefCell<Option<Box<dyn Iterator<Item = V> + 'i>>>,
+}
+impl<'i, 'v: 'i, V: Into<Scalar<'v>>> LazyValues<'i, V> {
+    pub fn new(values: impl IntoIterator<Item = V> + 'i) -> Self {
+        LazyValues {
+            values: RefCell::new(Some(Box::new(values.into_iter()))),
+        }
+    }
+}
+impl<'i, 'v: 'i, V: Into<Scalar<'v>>> TemplateValue for LazyValues<'i, V> {
+    fn to_value_ref<'s>(&'s self) -> Option<ValueRef<'s>> {
+        let values = self.values.borrow_mut().take()?;
+        Some(ValueRef::Values(Box::new(values.map(
+            |value| -> Scalar<'s> {
+                let value: Scalar<'v> = value.into();
+                value
+            },
+        ))))
+    }
+    fn is_present(&self) -> bool {
+        self.values.borrow().is_some()
+    }
+}
+
+/// Parameters for a `(ref)` tag, like [`TemplateParametersValue::ParametersVec`](crate::TemplateParametersValue::ParametersVec).
+pub struct LazyData<'i, D> {
+    data: RefCell<Option<Box<dyn Iterator<Item = D> + 'i>>>,
+}
+impl<'i, D: TemplateData> LazyData<'i, D> {
+    pub fn new(data: impl IntoIterator<Item = D> + 'i) -> Self {
+        LazyData {
+            data: RefCell::new(Some(Box::new(data.into_iter()))),
+        }
+    }
+}
+impl<D: TemplateData> TemplateValue for LazyData<'_, D> {
+    fn to_value_ref(&self) -> Option<ValueRef<'_>> {
+        let data = self.data.borrow_mut().take()?;
+        Some(ValueRef::DataIter(Box::new(
+            data.map(|data| DataRef::Owned(Box::new(data))),
+        )))
+    }
+    fn is_present(&self) -> bool {
+        self.data.borrow().is_some()
+    }
+}
+
+#[test]
+fn test_lazy() {
+    use crate::{IoSink, Parser, RenderOptions, Syntax, Templates};
+    use std::cell::Cell;
+
+    struct Row {
+        number: usize,
+    }
+    impl TemplateData for Row {
+        fn get(&self, name: &str) -> Option<ValueRef<'_>> {
+            match name {
+                "cell" => Some(ValueRef::Values(Box::new(
+                    [self.number, self.number * 10]
+                        .into_iter()
+                        .map(Scalar::from),
+                ))),
+                _ => None,
+            }
+        }
+        fn names(&self) -> Vec<&str> {
+            vec!["cell"]
+        }
+    }
+    struct Table<'i> {
+        rows: LazyData<'i, Row>,
+        captions: LazyValues<'i, &'static str>,
+    }
+    impl TemplateData for Table<'_> {
+        fn get(&self, name: &str) -> Option<ValueRef<'_>> {
+            match name {
+                "Row" => self.rows.to_value_ref(),
+                "caption" => self.captions.to_value_ref(),
+                _ => None,
+            }
+        }
+        fn names(&self) -> Vec<&str> {
+            [
+                ("Row", self.rows.is_present()),
+                ("caption", self.captions.is_present()),
+            ]
+            .into_iter()
+            .filter_map(|(name, is_present)| is_present.then_some(name))
+            .collect()
+        }
+    }
+
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([(
+        "Row",
+        parser.parse("<tr><!-- (param)cell --></tr>").unwrap(),
+    )]);
+    let template = parser
+        .parse("<table>\n<!-- (ref)Row -->\n</table><!-- (optional)(param)caption -->")
+        .unwrap();
+    let produced = Cell::new(0);
+    let table = Table {
+        rows: LazyData::new((1..=2).map(|number| {
+            produced.set(produced.get() + 1);
+            Row { number }
+        })),
+        captions: LazyValues::new(["a", "b"]),
+    };
+    assert!(table.rows.is_present());
+    assert_eq!(produced.get(), 0);
+    let mut sink = IoSink::new(Vec::new());
+    assert_eq!(
+        template
+            .render_to(&table, &templates, &RenderOptions::default(), &mut sink)
+            .unwrap(),
+        Vec::<String>::new()
+    );
+    assert_eq!(produced.get(), 2);
+    assert_eq!(
+        String::from_utf8(sink.into_inner().unwrap()).unwrap(),
+        "<table>\n<tr>1</tr>\n<tr>10</tr>\n<tr>2</tr>\n<tr>20</tr>\n</table>a\n</table>b"
+    );
+    assert!(table.names().is_empty());
+    assert_eq!(
+        template
+            .render_data(&table, &templates, &RenderOptions::default())
+            .unwrap(),
+        "<table>\n</table>"
+    );
+}
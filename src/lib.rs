@@ -1,11 +1,59 @@
-use regex::Regex;
-use std::collections::HashMap;
+use memchr::memmem;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-#[derive(Debug, PartialEq, Eq)]
+#[doc(hidden)]
+pub mod assemble;
+mod check;
+mod data;
+mod directory;
+mod escaper;
+#[cfg(feature = "serde")]
+mod json;
+mod lazy;
+#[cfg(feature = "regex")]
+mod regex_parser;
+mod scalar;
+mod schema;
+mod template_set;
+mod validate;
+
+pub use check::{Issue, IssueKind, Severity};
+pub use data::{DataRef, TemplateData, TemplateValue, TemplateVecItem, ValueRef};
+pub use directory::{DirectoryOptions, TemplateDirectory, TemplateNames};
+pub use escaper::{
+    Escaper, HtmlEscaper, JsonStringEscaper, NoEscaper, ShellEscaper, XmlAttributeEscaper,
+};
+#[cfg(feature = "serde")]
+pub use json::{JsonMismatch, parameters_from_json};
+pub use lazy::{LazyData, LazyValues};
+#[cfg(feature = "regex")]
+pub use regex_parser::RegexParser;
+pub use scalar::Scalar;
+pub use schema::{Conflict, Field, FieldKind, Schema, TemplateSchema};
+pub use template_set::TemplateSet;
+pub use validate::{Problem, validate};
+
+/// Tags and operators shared by parameters and references.
+///
+/// A tag prefixed with `escape_operator`, e.g. `<!-- (escape)(param)x -->`,
+/// is emitted literally with that one operator removed.
+/// A parameter marked with `raw_operator`, e.g. `<!-- (raw)(param)x -->`,
+/// is written without passing through [`RenderOptions::escaper`].
+/// A parameter marked with `indent_operator`, e.g. `<!-- (indent)(param)x -->`, repeats the text
+/// around it on its line, including the `(ref)` context, on every line of a multi-line value.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Syntax<'a> {
-    open_tag: &'a str,
-    close_tag: &'a str,
-    optional_operator: &'a str,
+    pub open_tag: &'a str,
+    pub close_tag: &'a str,
+    pub optional_operator: &'a str,
+    pub escape_operator: &'a str,
+    pub raw_operator: &'a str,
+    pub indent_operator: &'a str,
 }
 impl Default for Syntax<'_> {
     fn default() -> Self {
@@ -13,165 +61,848 @@ impl Default for Syntax<'_> {
             open_tag: "<!--",
             close_tag: "-->",
             optional_operator: "optional",
+            escape_operator: "escape",
+            raw_operator: "raw",
+            indent_operator: "indent",
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum ParametersLineToken<'a> {
-    Raw { value: &'a str },
-    Parameter { is_optional: bool, name: &'a str },
+/// Place in a template where rendering failed.
+///
+/// `references` is the chain of `(ref)` names followed from the root template,
+/// so its last element is the name of the template `line` and `column` point into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub references: Vec<String>,
+    pub line: usize,
+    pub column: usize,
 }
-#[derive(Debug, PartialEq, Eq)]
-enum Line<'a> {
-    Raw {
-        value: &'a str,
+impl Location {
+    fn new(references: &[&str], line: usize, column: usize) -> Self {
+        Location {
+            references: references.iter().map(|name| name.to_string()).collect(),
+            line,
+            column,
+        }
+    }
+    pub fn template(&self) -> Option<&str> {
+        self.references.last().map(String::as_str)
+    }
+}
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.template() {
+            Some(template) => write!(f, "template \"{template}\"")?,
+            None => write!(f, "root template")?,
+        }
+        write!(f, ", line {}, column {}", self.line, self.column)?;
+        if self.references.len() > 1 {
+            write!(f, " (referenced via {})", self.references.join(" -> "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Variants depend on enabled features, so matches need a wildcard arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[cfg(feature = "regex")]
+    TagRegex(regex::Error),
+    Io(io::Error),
+    Fmt(fmt::Error),
+    UnknownTemplate {
+        name: String,
     },
+    ReadTemplate {
+        path: PathBuf,
+        error: io::Error,
+    },
+    DuplicateTemplate {
+        name: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+    TemplateName {
+        path: PathBuf,
+    },
+    #[cfg(feature = "serde")]
+    Json {
+        path: String,
+        mismatch: JsonMismatch,
+    },
+    #[cfg(feature = "serde")]
+    Serialize(serde_json::Error),
+    MissingParameter {
+        name: String,
+        location: Location,
+    },
+    ExpectedValue {
+        name: String,
+        location: Location,
+    },
+    EmptyValuesVec {
+        name: String,
+        location: Location,
+    },
+    ExpectedParameters {
+        name: String,
+        location: Location,
+    },
+    MissingTemplate {
+        name: String,
+        location: Location,
+    },
+    DepthLimit {
+        name: String,
+        location: Location,
+    },
+    UnusedParameters {
+        paths: Vec<String>,
+    },
+}
+impl Error {
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            #[cfg(feature = "regex")]
+            Error::TagRegex(_) => None,
+            Error::Io(_)
+            | Error::Fmt(_)
+            | Error::UnknownTemplate { .. }
+            | Error::ReadTemplate { .. }
+            | Error::DuplicateTemplate { .. }
+            | Error::TemplateName { .. }
+            | Error::UnusedParameters { .. } => None,
+            #[cfg(feature = "serde")]
+            Error::Json { .. } | Error::Serialize(_) => None,
+            Error::MissingParameter { location, .. }
+            | Error::ExpectedValue { location, .. }
+            | Error::EmptyValuesVec { location, .. }
+            | Error::ExpectedParameters { location, .. }
+            | Error::MissingTemplate { location, .. }
+            | Error::DepthLimit { location, .. } => Some(location),
+        }
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "regex")]
+            Error::TagRegex(error) => write!(f, "Can not parse tag regex: {error}"),
+            Error::Io(error) => write!(f, "Can not write rendered template: {error}"),
+            Error::Fmt(error) => write!(f, "Can not format rendered template: {error}"),
+            Error::UnknownTemplate { name } => write!(f, "No template named \"{name}\""),
+            Error::ReadTemplate { path, error } => {
+                write!(f, "Can not read \"{}\": {error}", path.display())
+            }
+            Error::DuplicateTemplate {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "Template name \"{name}\" derived from both \"{}\" and \"{}\"",
+                first.display(),
+                second.display()
+            ),
+            Error::TemplateName { path } => write!(
+                f,
+                "Can not derive template name from \"{}\"",
+                path.display()
+            ),
+            #[cfg(feature = "serde")]
+            Error::Json { path, mismatch } => {
+                write!(
+                    f,
+                    "Can not convert JSON at \"{path}\" to template parameters: {mismatch}"
+                )
+            }
+            #[cfg(feature = "serde")]
+            Error::Serialize(error) => write!(f, "Can not serialize template data: {error}"),
+            Error::MissingParameter { name, location } => {
+                write!(f, "Expected key for parameter \"{name}\" at {location}")
+            }
+            Error::ExpectedValue { name, location } => write!(
+                f,
+                "Expected value or non-empty Vec of values for parameter \"{name}\" at {location}"
+            ),
+            Error::EmptyValuesVec { name, location } => write!(
+                f,
+                "Expected non-empty Vec of values for parameter \"{name}\" at {location}"
+            ),
+            Error::ExpectedParameters { name, location } => write!(
+                f,
+                "Expected template parameters or template parameters Vec for template reference \"{name}\" at {location}"
+            ),
+            Error::MissingTemplate { name, location } => write!(
+                f,
+                "No template provided for template reference \"{name}\" at {location}"
+            ),
+            Error::DepthLimit { name, location } => write!(
+                f,
+                "Maximum references depth exceeded by template reference \"{name}\" at {location}"
+            ),
+            Error::UnusedParameters { paths } => {
+                write!(f, "Parameters not used by any template: ")?;
+                for (index, path) in paths.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "\"{path}\"")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "regex")]
+            Error::TagRegex(error) => Some(error),
+            Error::Io(error) => Some(error),
+            Error::ReadTemplate { error, .. } => Some(error),
+            Error::Fmt(error) => Some(error),
+            #[cfg(feature = "serde")]
+            Error::Serialize(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Part of a [`Line::Parameters`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParametersLineToken<'a> {
+    /// Text between tags, with escaped tags already unescaped.
+    Raw { value: Cow<'a, str> },
+    Parameter {
+        is_optional: bool,
+        is_raw: bool,
+        is_indent: bool,
+        name: Cow<'a, str>,
+        /// One-based, in characters.
+        column: usize,
+    },
+    /// Reference sharing its line with parameters or other references. The line is written
+    /// once per combination of lines rendered by its references, see [`Line::Parameters`].
+    Reference {
+        is_optional: bool,
+        name: Cow<'a, str>,
+        /// One-based, in characters.
+        column: usize,
+    },
+}
+/// Parsed template line, as seen by code generators like `drunk_snail_macros`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line<'a> {
+    /// Line without tags, rendered as is.
+    Raw { value: Cow<'a, str> },
+    /// Line with parameters, repeated once per value of the shortest values Vec, or with
+    /// several references. Repetition stops as soon as any values Vec, or any non-optional
+    /// single Value, is exhausted: `a = [1, 2, 3]` and `b = [1]` render the single line `1 1`
+    /// for `<!-- (param)a --> <!-- (param)b -->`. Each line rendered by a reference is written with the text around
+    /// the tag, like [`Line::Reference`] does; with several references, with every combination
+    /// of their lines. A reference that renders nothing removes the line.
     Parameters {
         tokens: Vec<ParametersLineToken<'a>>,
     },
+    /// Line with a single reference and no parameters, the referenced template is rendered
+    /// with `left` and `right` around each of its lines.
     Reference {
-        left: Option<&'a str>,
+        left: Option<Cow<'a, str>>,
         is_optional: bool,
-        name: &'a str,
-        right: Option<&'a str>,
+        name: Cow<'a, str>,
+        /// One-based, in characters.
+        column: usize,
+        right: Option<Cow<'a, str>>,
     },
 }
-#[derive(Debug, PartialEq, Eq)]
+fn into_owned(value: Cow<str>) -> Cow<'static, str> {
+    Cow::Owned(value.into_owned())
+}
+impl ParametersLineToken<'_> {
+    pub fn into_owned(self) -> ParametersLineToken<'static> {
+        match self {
+            ParametersLineToken::Raw { value } => ParametersLineToken::Raw {
+                value: into_owned(value),
+            },
+            ParametersLineToken::Parameter {
+                is_optional,
+                is_raw,
+                is_indent,
+                name,
+                column,
+            } => ParametersLineToken::Parameter {
+                is_optional,
+                is_raw,
+                is_indent,
+                name: into_owned(name),
+                column,
+            },
+            ParametersLineToken::Reference {
+                is_optional,
+                name,
+                column,
+            } => ParametersLineToken::Reference {
+                is_optional,
+                name: into_owned(name),
+                column,
+            },
+        }
+    }
+}
+impl Line<'_> {
+    pub fn into_owned(self) -> Line<'static> {
+        match self {
+            Line::Raw { value } => Line::Raw {
+                value: into_owned(value),
+            },
+            Line::Parameters { tokens } => Line::Parameters {
+                tokens: tokens
+                    .into_iter()
+                    .map(ParametersLineToken::into_owned)
+                    .collect(),
+            },
+            Line::Reference {
+                left,
+                is_optional,
+                name,
+                column,
+                right,
+            } => Line::Reference {
+                left: left.map(into_owned),
+                is_optional,
+                name: into_owned(name),
+                column,
+                right: right.map(into_owned),
+            },
+        }
+    }
+}
+
+/// Terminator of a template line as found in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    /// Last line of a source that does not end with a line break.
+    None,
+}
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::None => "",
+        }
+    }
+    /// Splits like `str::lines`, keeping what terminated each line.
+    pub fn split(text: &str) -> impl Iterator<Item = (&str, LineEnding)> {
+        text.split_inclusive('\n').map(|line| {
+            if let Some(line) = line.strip_suffix("\r\n") {
+                (line, LineEnding::CrLf)
+            } else if let Some(line) = line.strip_suffix('\n') {
+                (line, LineEnding::Lf)
+            } else {
+                (line, LineEnding::None)
+            }
+        })
+    }
+}
+
+/// Parsed template. Text of lines and tags is borrowed from the source unless the template
+/// is made `'static` with [`Template::into_owned`] or [`Parser::parse_owned`].
+///
+/// Lines are shared between clones, so cloning is cheap, e.g. to put stored templates into
+/// [`Templates`] for every render.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Template<'a> {
-    lines: Vec<Line<'a>>,
+    lines: Arc<[Line<'a>]>,
+    line_endings: Arc<[LineEnding]>,
+}
+impl<'a> Template<'a> {
+    pub(crate) fn new(lines: Vec<Line<'a>>, line_endings: Vec<LineEnding>) -> Self {
+        Template {
+            lines: lines.into(),
+            line_endings: line_endings.into(),
+        }
+    }
+    /// Copies borrowed text, so that the template no longer depends on its source.
+    pub fn into_owned(self) -> Template<'static> {
+        Template {
+            lines: self.lines.iter().cloned().map(Line::into_owned).collect(),
+            line_endings: self.line_endings,
+        }
+    }
+    pub fn lines(&self) -> &[Line<'a>] {
+        &self.lines
+    }
+    /// Terminator of each of [`Template::lines`].
+    pub fn line_endings(&self) -> &[LineEnding] {
+        &self.line_endings
+    }
+    /// Every reference tag, on its own line or inline, in order.
+    pub fn references(&self) -> impl Iterator<Item = ReferenceTag<'_>> {
+        self.lines
+            .iter()
+            .enumerate()
+            .flat_map(|(line_index, line)| {
+                let tokens = match line {
+                    Line::Parameters { tokens } => tokens.as_slice(),
+                    _ => &[],
+                };
+                let reference = match line {
+                    Line::Reference {
+                        is_optional,
+                        name,
+                        column,
+                        ..
+                    } => Some((name.as_ref(), *is_optional, *column)),
+                    _ => None,
+                };
+                reference
+                    .into_iter()
+                    .chain(tokens.iter().filter_map(|token| match token {
+                        ParametersLineToken::Reference {
+                            is_optional,
+                            name,
+                            column,
+                        } => Some((name.as_ref(), *is_optional, *column)),
+                        _ => None,
+                    }))
+                    .map(move |(name, is_optional, column)| ReferenceTag {
+                        name,
+                        is_optional,
+                        line: line_index + 1,
+                        column,
+                    })
+            })
+    }
+}
+
+/// `(ref)` tag of a template, see [`Template::references`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferenceTag<'a> {
+    pub name: &'a str,
+    pub is_optional: bool,
+    /// One-based.
+    pub line: usize,
+    /// One-based, in characters.
+    pub column: usize,
+}
+
+fn column(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum TagKind {
+    Parameter,
+    Reference,
+}
+
+struct Tag<'a> {
+    kind: TagKind,
+    is_optional: bool,
+    is_raw: bool,
+    is_indent: bool,
+    name: &'a str,
+    start: usize,
+    end: usize,
 }
 
 #[derive(Debug)]
 pub struct Parser {
-    parameter_regex: Regex,
-    reference_line_regex: Regex,
+    open_tag_finder: memmem::Finder<'static>,
+    close_tag: String,
+    optional_operator: String,
+    escape_operator: String,
+    raw_operator: String,
+    indent_operator: String,
+    parameter_operator: String,
+    reference_operator: String,
 }
 impl Parser {
     pub fn from_syntax(
         syntax: &Syntax,
         parameter_operator: &str,
         reference_operator: &str,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, Error> {
         Ok(Parser {
-                parameter_regex: Regex::new(
-                    format!(r"(?P<left>.+?)?{} *(?P<optional>\({}\))?\({}\)(?P<name>\w+) *{}",
-                        regex::escape(syntax.open_tag),
-                        regex::escape(syntax.optional_operator),
-                        regex::escape(parameter_operator),
-                        regex::escape(syntax.close_tag)
-                    ).as_str(),
-                ).map_err(|error| format!("Can not parse parameter regex: {error}"))?,
-                reference_line_regex: Regex::new(
-                    format!(
-                        r"^(?P<left>.+)?{} *(?P<optional>\({}\))?\({}\)(?P<name>\w+) *{}(?P<right>.+)?$",
-                        regex::escape(syntax.open_tag),
-                        regex::escape(syntax.optional_operator),
-                        regex::escape(reference_operator),
-                        regex::escape(syntax.close_tag)
-                    ).as_str(),
-                ).map_err(|error| format!("Can not parse reference line regex: {error}"))?,
-            })
+            open_tag_finder: memmem::Finder::new(syntax.open_tag).into_owned(),
+            close_tag: syntax.close_tag.to_string(),
+            optional_operator: syntax.optional_operator.to_string(),
+            escape_operator: syntax.escape_operator.to_string(),
+            raw_operator: syntax.raw_operator.to_string(),
+            indent_operator: syntax.indent_operator.to_string(),
+            parameter_operator: parameter_operator.to_string(),
+            reference_operator: reference_operator.to_string(),
+        })
     }
-    pub fn parse<'a>(&'a self, text: &'a str) -> Result<Template<'a>, String> {
-        let mut parsed_lines: Vec<Line> = Vec::new();
-        for line in text.lines() {
-            parsed_lines.push({
-                let parameters_captures: Vec<_> =
-                    self.parameter_regex.captures_iter(line).collect();
-                if parameters_captures.len() > 0 {
-                    Line::Parameters {
-                        tokens: {
-                            let mut result: Vec<ParametersLineToken> = Vec::new();
-                            for capture in &parameters_captures {
-                                if let Some(left) = capture.name("left") {
-                                    result.push(ParametersLineToken::Raw {
-                                        value: left.as_str(),
-                                    });
-                                }
-                                result.push(ParametersLineToken::Parameter {
-                                    is_optional: capture.name("optional").is_some(),
-                                    name: capture
-                                        .name("name")
-                                        .ok_or(format!(
-                                            "Can not get parameter name from line {line}"
-                                        ))?
-                                        .as_str(),
-                                })
-                            }
-                            let right = &line[parameters_captures
-                                .last()
-                                .ok_or(format!(
-                                    "Can not get last parameter captures from line {line}"
-                                ))?
-                                .get_match()
-                                .end()..];
-                            result.push(ParametersLineToken::Raw { value: right });
-                            result
-                        },
-                    }
-                } else if let Some(captures) = self.reference_line_regex.captures(line) {
-                    Line::Reference {
-                        left: if let Some(left_match) = captures.name("left") {
-                            Some(left_match.as_str())
-                        } else {
-                            None
-                        },
-                        is_optional: captures.name("optional").is_some(),
-                        name: captures
-                            .name("name")
-                            .ok_or(format!("Can not get parameter name from line {line}"))?
-                            .as_str(),
-                        right: if let Some(left_match) = captures.name("right") {
-                            Some(left_match.as_str())
-                        } else {
-                            None
-                        },
+    fn skip_spaces(line: &str, position: usize) -> usize {
+        position + line[position..].len() - line[position..].trim_start_matches(' ').len()
+    }
+    fn skip_operator(line: &str, position: usize, operator: &str) -> Option<usize> {
+        let rest = line[position..]
+            .strip_prefix('(')?
+            .strip_prefix(operator)?
+            .strip_prefix(')')?;
+        Some(line.len() - rest.len())
+    }
+    /// Parses `(operator)name *close_tag` starting at `position`, backtracking over
+    /// the name the same way `\w+ *` would if the close tag itself starts with word characters.
+    fn parse_tag_body<'a>(
+        &self,
+        line: &'a str,
+        position: usize,
+    ) -> Option<(TagKind, &'a str, usize)> {
+        let (kind, name_start) = if let Some(name_start) =
+            Self::skip_operator(line, position, &self.parameter_operator)
+        {
+            (TagKind::Parameter, name_start)
+        } else {
+            (
+                TagKind::Reference,
+                Self::skip_operator(line, position, &self.reference_operator)?,
+            )
+        };
+        let word_length = |start: usize| {
+            line[start..]
+                .find(|character: char| !(character.is_alphanumeric() || character == '_'))
+                .unwrap_or(line.len() - start)
+        };
+        let mut name_end = name_start + word_length(name_start);
+        // Reference names can be namespaced, e.g. `pages.Row` or `pages/Row`, see
+        // `TemplateNames`.
+        while kind == TagKind::Reference
+            && line[name_end..].starts_with(['.', '/'])
+            && word_length(name_end + 1) > 0
+        {
+            name_end += 1 + word_length(name_end + 1);
+        }
+        while name_end > name_start {
+            let close_start = Self::skip_spaces(line, name_end);
+            if line[close_start..].starts_with(self.close_tag.as_str()) {
+                return Some((
+                    kind,
+                    &line[name_start..name_end],
+                    close_start + self.close_tag.len(),
+                ));
+            }
+            name_end = line[name_start..name_end]
+                .char_indices()
+                .next_back()
+                .map_or(name_start, |(index, _)| name_start + index);
+        }
+        None
+    }
+    /// Parses `[(optional)][(raw)][(indent)](operator)name *close_tag`, modifiers in any order,
+    /// starting at `position` right after the open tag and spaces.
+    fn parse_tag_at<'a>(&self, line: &'a str, start: usize, position: usize) -> Option<Tag<'a>> {
+        let mut is_optional = false;
+        let mut is_raw = false;
+        let mut is_indent = false;
+        let mut position = position;
+        loop {
+            if let Some(after) = Self::skip_operator(line, position, &self.optional_operator)
+                && !is_optional
+            {
+                is_optional = true;
+                position = after;
+            } else if let Some(after) = Self::skip_operator(line, position, &self.raw_operator)
+                && !is_raw
+            {
+                is_raw = true;
+                position = after;
+            } else if let Some(after) = Self::skip_operator(line, position, &self.indent_operator)
+                && !is_indent
+            {
+                is_indent = true;
+                position = after;
+            } else {
+                break;
+            }
+        }
+        let (kind, name, end) = self.parse_tag_body(line, position)?;
+        Some(Tag {
+            kind,
+            is_optional,
+            is_raw,
+            is_indent,
+            name,
+            start,
+            end,
+        })
+    }
+    /// Returns the end of `[(escape)]*` followed by a tag starting at `position`.
+    fn parse_tag_end(&self, line: &str, position: usize) -> Option<usize> {
+        if let Some(after_escape) = Self::skip_operator(line, position, &self.escape_operator)
+            && let Some(end) = self.parse_tag_end(line, after_escape)
+        {
+            return Some(end);
+        }
+        self.parse_tag_at(line, position, position)
+            .map(|tag| tag.end)
+    }
+    /// Returns the span of the escape operator and the end of the escaped tag starting at `start`.
+    fn parse_escaped_tag(&self, line: &str, start: usize) -> Option<(usize, usize, usize)> {
+        let operator_start = Self::skip_spaces(line, start + self.open_tag_finder.needle().len());
+        let operator_end = Self::skip_operator(line, operator_start, &self.escape_operator)?;
+        let end = self.parse_tag_end(line, operator_end)?;
+        Some((operator_start, operator_end, end))
+    }
+    fn parse_tag<'a>(&self, line: &'a str, start: usize) -> Option<Tag<'a>> {
+        let position = Self::skip_spaces(line, start + self.open_tag_finder.needle().len());
+        self.parse_tag_at(line, start, position)
+    }
+    /// Returns `line[range]` without the escape operators inside it.
+    fn unescape<'a>(
+        line: &'a str,
+        range: std::ops::Range<usize>,
+        escapes: &[std::ops::Range<usize>],
+    ) -> Option<Cow<'a, str>> {
+        if range.is_empty() {
+            return None;
+        }
+        let inner: Vec<_> = escapes
+            .iter()
+            .filter(|escape| range.start <= escape.start && escape.end <= range.end)
+            .collect();
+        if inner.is_empty() {
+            return Some(Cow::Borrowed(&line[range]));
+        }
+        let mut result = String::new();
+        let mut start = range.start;
+        for escape in inner {
+            result.push_str(&line[start..escape.start]);
+            start = escape.end;
+        }
+        result.push_str(&line[start..range.end]);
+        Some(Cow::Owned(result))
+    }
+    fn parse_line<'a>(&self, line: &'a str) -> Line<'a> {
+        let mut tokens: Vec<ParametersLineToken> = Vec::new();
+        let mut has_parameters = false;
+        let mut escapes = Vec::new();
+        let mut references: Vec<Tag> = Vec::new();
+        let mut raw_start = 0;
+        let mut search_start = 0;
+        while search_start <= line.len() {
+            let Some(found) = self.open_tag_finder.find(&line.as_bytes()[search_start..]) else {
+                break;
+            };
+            let start = search_start + found;
+            if let Some((operator_start, operator_end, end)) = self.parse_escaped_tag(line, start) {
+                tokens.push(ParametersLineToken::Raw {
+                    value: line[raw_start..operator_start].into(),
+                });
+                escapes.push(operator_start..operator_end);
+                raw_start = operator_end;
+                search_start = end;
+                continue;
+            }
+            if let Some(tag) = self.parse_tag(line, start) {
+                if start > raw_start {
+                    tokens.push(ParametersLineToken::Raw {
+                        value: line[raw_start..start].into(),
+                    });
+                }
+                tokens.push(match tag.kind {
+                    TagKind::Parameter => {
+                        has_parameters = true;
+                        ParametersLineToken::Parameter {
+                            is_optional: tag.is_optional,
+                            is_raw: tag.is_raw,
+                            is_indent: tag.is_indent,
+                            name: tag.name.into(),
+                            column: column(line, start),
+                        }
                     }
-                } else {
-                    Line::Raw { value: line }
+                    TagKind::Reference => ParametersLineToken::Reference {
+                        is_optional: tag.is_optional,
+                        name: tag.name.into(),
+                        column: column(line, start),
+                    },
+                });
+                raw_start = tag.end;
+                search_start = tag.end;
+                if tag.kind == TagKind::Reference {
+                    references.push(tag);
                 }
-            });
+                continue;
+            }
+            search_start = line[start..]
+                .chars()
+                .next()
+                .map_or(line.len() + 1, |character| start + character.len_utf8());
         }
-        Ok(Template {
-            lines: parsed_lines,
-        })
+        match references.as_slice() {
+            [reference] if !has_parameters => Line::Reference {
+                left: Self::unescape(line, 0..reference.start, &escapes),
+                is_optional: reference.is_optional,
+                name: reference.name.into(),
+                column: column(line, reference.start),
+                right: Self::unescape(line, reference.end..line.len(), &escapes),
+            },
+            _ if !tokens.is_empty() => {
+                tokens.push(ParametersLineToken::Raw {
+                    value: line[raw_start..].into(),
+                });
+                Line::Parameters { tokens }
+            }
+            _ => Line::Raw { value: line.into() },
+        }
+    }
+    /// Borrows text of lines and tags from `text`, see [`Parser::parse_owned`] for a template
+    /// that does not.
+    pub fn parse<'a>(&self, text: &'a str) -> Result<Template<'a>, Error> {
+        let (lines, line_endings) = LineEnding::split(text)
+            .map(|(line, line_ending)| (self.parse_line(line), line_ending))
+            .unzip();
+        Ok(Template::new(lines, line_endings))
+    }
+    /// Parses into a `'static` template, e.g. for caches or for sources read from files.
+    pub fn parse_owned(&self, text: impl AsRef<str>) -> Result<Template<'static>, Error> {
+        Ok(self.parse(text.as_ref())?.into_owned())
     }
 }
 
 pub enum TemplateParametersValue<'a> {
     Parameters(TemplateParameters<'a>),
-    ValuesVec(Vec<String>),
+    ValuesVec(Vec<Scalar<'a>>),
     ParametersVec(Vec<TemplateParameters<'a>>),
-    Value(String),
+    Value(Scalar<'a>),
+}
+
+/// Parameters by name. Names are borrowed, e.g. `params! {"cell" => ..}`, or owned when
+/// they come from runtime data, e.g. `params! {column.name.clone() => ..}`.
+#[derive(Default)]
+pub struct TemplateParameters<'a> {
+    values: HashMap<Cow<'a, str>, TemplateParametersValue<'a>>,
+}
+impl<'a> TemplateParameters<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        TemplateParameters {
+            values: HashMap::with_capacity(capacity),
+        }
+    }
+    pub fn insert(
+        &mut self,
+        name: impl Into<Cow<'a, str>>,
+        value: TemplateParametersValue<'a>,
+    ) -> Option<TemplateParametersValue<'a>> {
+        self.values.insert(name.into(), value)
+    }
+    pub fn get(&self, name: &str) -> Option<&TemplateParametersValue<'a>> {
+        self.values.get(name)
+    }
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut TemplateParametersValue<'a>> {
+        self.values.get_mut(name)
+    }
+    pub fn remove(&mut self, name: &str) -> Option<TemplateParametersValue<'a>> {
+        self.values.remove(name)
+    }
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(AsRef::as_ref)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TemplateParametersValue<'a>)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value))
+    }
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+impl<'a, K: Into<Cow<'a, str>>> FromIterator<(K, TemplateParametersValue<'a>)>
+    for TemplateParameters<'a>
+{
+    fn from_iter<I: IntoIterator<Item = (K, TemplateParametersValue<'a>)>>(iter: I) -> Self {
+        TemplateParameters {
+            values: iter
+                .into_iter()
+                .map(|(name, value)| (name.into(), value))
+                .collect(),
+        }
+    }
+}
+impl<'a, K: Into<Cow<'a, str>>, const N: usize> From<[(K, TemplateParametersValue<'a>); N]>
+    for TemplateParameters<'a>
+{
+    fn from(values: [(K, TemplateParametersValue<'a>); N]) -> Self {
+        values.into_iter().collect()
+    }
+}
+impl<'a, K: Into<Cow<'a, str>>> From<HashMap<K, TemplateParametersValue<'a>>>
+    for TemplateParameters<'a>
+{
+    fn from(values: HashMap<K, TemplateParametersValue<'a>>) -> Self {
+        values.into_iter().collect()
+    }
+}
+impl<'a, K: Into<Cow<'a, str>>> From<BTreeMap<K, TemplateParametersValue<'a>>>
+    for TemplateParameters<'a>
+{
+    fn from(values: BTreeMap<K, TemplateParametersValue<'a>>) -> Self {
+        values.into_iter().collect()
+    }
+}
+impl<'a> From<TemplateParameters<'a>> for TemplateParametersValue<'a> {
+    fn from(parameters: TemplateParameters<'a>) -> Self {
+        TemplateParametersValue::Parameters(parameters)
+    }
 }
-pub type TemplateParameters<'a> = HashMap<&'a str, TemplateParametersValue<'a>>;
+impl<'a, K: Into<Cow<'a, str>>> From<HashMap<K, TemplateParametersValue<'a>>>
+    for TemplateParametersValue<'a>
+{
+    fn from(parameters: HashMap<K, TemplateParametersValue<'a>>) -> Self {
+        TemplateParametersValue::Parameters(parameters.into())
+    }
+}
+impl<'a, K: Into<Cow<'a, str>>> From<BTreeMap<K, TemplateParametersValue<'a>>>
+    for TemplateParametersValue<'a>
+{
+    fn from(parameters: BTreeMap<K, TemplateParametersValue<'a>>) -> Self {
+        TemplateParametersValue::Parameters(parameters.into())
+    }
+}
+
 pub type Templates<'a> = HashMap<&'a str, Template<'a>>;
 
 #[macro_export]
 macro_rules! tp_value {
     ($val:expr) => {
-        TemplateParametersValue::Value($val)
+        TemplateParametersValue::Value($crate::Scalar::from($val))
     };
 }
 
 #[macro_export]
 macro_rules! tp_values {
     ($($val:expr),*) => {
-        TemplateParametersValue::ValuesVec(vec![$($val.to_string()),*])
+        TemplateParametersValue::ValuesVec(vec![$($crate::Scalar::from($val)),*])
     };
 }
 
 #[macro_export]
 macro_rules! tp_params {
+    () => {
+        TemplateParametersValue::Parameters($crate::TemplateParameters::new())
+    };
     ($($key:expr => $value:expr),*) => {{
-        let mut params = HashMap::new();
+        let mut params = $crate::TemplateParameters::new();
         $(params.insert($key, $value);)*
         TemplateParametersValue::Parameters(params)
     }};
-    () => {
-        TemplateParametersValue::Parameters(HashMap::new())
-    };
 }
 
 #[macro_export]
@@ -183,178 +914,732 @@ macro_rules! tp_params_vec {
 
 #[macro_export]
 macro_rules! params {
+    () => {
+        $crate::TemplateParameters::new()
+    };
     ($($key:expr => $value:expr),*) => {{
-        let mut map = HashMap::new();
+        let mut map = $crate::TemplateParameters::new();
         $(map.insert($key, $value);)*
         map
     }};
-    () => {
-        HashMap::new()
-    };
 }
 
-impl Template<'_> {
-    pub fn render_internal(
-        &self,
-        parameters: &TemplateParameters,
-        templates: &Templates,
+/// Destination of rendered text.
+///
+/// Implemented for `String` and for the [`IoSink`] and [`FmtSink`] adapters, so that
+/// rendering can stream straight into files, sockets or formatters.
+pub trait Sink {
+    fn push_str(&mut self, value: &str) -> Result<(), Error>;
+    /// Writes formatted values, e.g. numbers, without an intermediate `String` where possible.
+    fn push_fmt(&mut self, arguments: fmt::Arguments) -> Result<(), Error> {
+        self.push_str(&arguments.to_string())
+    }
+}
+impl Sink for String {
+    fn push_str(&mut self, value: &str) -> Result<(), Error> {
+        String::push_str(self, value);
+        Ok(())
+    }
+    fn push_fmt(&mut self, arguments: fmt::Arguments) -> Result<(), Error> {
+        fmt::Write::write_fmt(self, arguments).map_err(Error::Fmt)
+    }
+}
+
+/// [`Sink`] writing into any `std::io::Write` through a `BufWriter`.
+pub struct IoSink<W: io::Write> {
+    writer: io::BufWriter<W>,
+}
+impl<W: io::Write> IoSink<W> {
+    pub fn new(writer: W) -> Self {
+        IoSink {
+            writer: io::BufWriter::new(writer),
+        }
+    }
+    pub fn into_inner(self) -> Result<W, Error> {
+        self.writer
+            .into_inner()
+            .map_err(|error| Error::Io(error.into_error()))
+    }
+}
+impl<W: io::Write> Sink for IoSink<W> {
+    fn push_str(&mut self, value: &str) -> Result<(), Error> {
+        io::Write::write_all(&mut self.writer, value.as_bytes()).map_err(Error::Io)
+    }
+    fn push_fmt(&mut self, arguments: fmt::Arguments) -> Result<(), Error> {
+        io::Write::write_fmt(&mut self.writer, arguments).map_err(Error::Io)
+    }
+}
+
+/// [`Sink`] writing into any `std::fmt::Write`.
+pub struct FmtSink<W: fmt::Write> {
+    writer: W,
+}
+impl<W: fmt::Write> FmtSink<W> {
+    pub fn new(writer: W) -> Self {
+        FmtSink { writer }
+    }
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+impl<W: fmt::Write> Sink for FmtSink<W> {
+    fn push_str(&mut self, value: &str) -> Result<(), Error> {
+        self.writer.write_str(value).map_err(Error::Fmt)
+    }
+    fn push_fmt(&mut self, arguments: fmt::Arguments) -> Result<(), Error> {
+        self.writer.write_fmt(arguments).map_err(Error::Fmt)
+    }
+}
+
+/// What to do with keys of template parameters that no tag of the rendered templates uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnusedParameters {
+    #[default]
+    Ignore,
+    /// Return their paths from [`Template::render_to`].
+    Warn,
+    /// Fail with [`Error::UnusedParameters`] after rendering.
+    Error,
+}
+
+/// Line terminators written after rendered lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEndings {
+    /// As found in the template sources, see [`Template::line_endings`].
+    #[default]
+    Preserve,
+    Lf,
+    CrLf,
+}
+
+/// Settings applied to a single render call.
+#[derive(Clone, Copy)]
+pub struct RenderOptions<'o> {
+    /// Applied to every parameter value not marked with the raw operator.
+    pub escaper: &'o dyn Escaper,
+    /// Maximum number of nested references, for templates that are recursive on purpose.
+    pub max_depth: Option<usize>,
+    /// Fail with [`Error::MissingParameter`] when the key of a non-optional reference is absent,
+    /// instead of rendering nothing for it.
+    pub strict_references: bool,
+    /// Detection of keys no tag reads, which are usually typos like `Rows` for `Row`.
+    pub unused_parameters: UnusedParameters,
+    pub line_endings: LineEndings,
+}
+impl Default for RenderOptions<'_> {
+    fn default() -> Self {
+        RenderOptions {
+            escaper: &NoEscaper,
+            max_depth: None,
+            strict_references: false,
+            unused_parameters: UnusedParameters::Ignore,
+            line_endings: LineEndings::Preserve,
+        }
+    }
+}
+
+/// Parameter of a parameters line, looked up once before the line is rendered.
+enum LineValue<'v> {
+    Missing,
+    Value(Scalar<'v>),
+    Values(std::iter::Peekable<Box<dyn Iterator<Item = Scalar<'v>> + 'v>>),
+}
+
+struct Renderer<'t, 'o, S: Sink> {
+    templates: &'t Templates<'t>,
+    options: &'o RenderOptions<'o>,
+    references: Vec<&'t str>,
+    /// Path of the parameters being rendered, e.g. `Row[3]`, kept only when reporting unused ones.
+    path: String,
+    unused: Vec<String>,
+    /// Reused for formatting values other than strings that have to be escaped.
+    buffer: String,
+    /// Terminator of the last written line, see [`Renderer::start_line`].
+    pending_line_ending: Option<LineEnding>,
+    /// Written between repeats of an unterminated line: the ending of the enclosing
+    /// reference line, or of the previous line of the root template.
+    separator: LineEnding,
+    result: &'o mut S,
+}
+impl<'t, S: Sink> Renderer<'t, '_, S> {
+    fn location(&self, line_index: usize, column: usize) -> Location {
+        Location::new(&self.references, line_index + 1, column)
+    }
+    fn collect_unused(&mut self, template: &Template, parameters: &dyn TemplateData) {
+        let mut unused: Vec<&str> = parameters
+            .names()
+            .into_iter()
+            .filter(|name| !template.uses(name))
+            .collect();
+        unused.sort_unstable();
+        for name in unused {
+            let path = if self.path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{name}", self.path)
+            };
+            // The same parameters are visited again when several tags reference them.
+            if !self.unused.contains(&path) {
+                self.unused.push(path);
+            }
+        }
+    }
+    fn push_line_ending(&mut self, line_ending: LineEnding) -> Result<(), Error> {
+        assemble::push_line_ending(self.result, self.options.line_endings, line_ending)
+    }
+    fn start_line(&mut self) -> Result<(), Error> {
+        assemble::start_line(
+            self.result,
+            &mut self.pending_line_ending,
+            self.options.line_endings,
+            self.separator,
+        )
+    }
+    fn finish(&mut self) -> Result<(), Error> {
+        match self.pending_line_ending.take() {
+            Some(LineEnding::None) | None => Ok(()),
+            Some(line_ending) => self.push_line_ending(line_ending),
+        }
+    }
+    fn push_value(&mut self, value: &Scalar, is_raw: bool) -> Result<(), Error> {
+        match value {
+            Scalar::Str(value) if is_raw => self.result.push_str(value),
+            Scalar::Str(value) => self.result.push_str(&self.options.escaper.escape(value)),
+            _ if is_raw || self.options.escaper.escapes_nothing() => {
+                self.result.push_fmt(format_args!("{value}"))
+            }
+            _ => {
+                self.buffer.clear();
+                fmt::Write::write_fmt(&mut self.buffer, format_args!("{value}"))
+                    .map_err(Error::Fmt)?;
+                self.result
+                    .push_str(&self.options.escaper.escape(&self.buffer))
+            }
+        }
+    }
+    fn format_value(
+        &mut self,
+        line: &mut String,
+        value: &Scalar,
+        is_raw: bool,
+    ) -> Result<(), Error> {
+        match value {
+            Scalar::Str(value) if is_raw => line.push_str(value),
+            Scalar::Str(value) => line.push_str(&self.options.escaper.escape(value)),
+            _ if is_raw || self.options.escaper.escapes_nothing() => {
+                fmt::Write::write_fmt(line, format_args!("{value}")).map_err(Error::Fmt)?
+            }
+            _ => {
+                self.buffer.clear();
+                fmt::Write::write_fmt(&mut self.buffer, format_args!("{value}"))
+                    .map_err(Error::Fmt)?;
+                line.push_str(&self.options.escaper.escape(&self.buffer))
+            }
+        }
+        Ok(())
+    }
+    fn push_line_part(
+        &mut self,
+        has_indent: bool,
+        line: &mut String,
+        value: &str,
+    ) -> Result<(), Error> {
+        if has_indent {
+            line.push_str(value);
+            Ok(())
+        } else {
+            self.result.push_str(value)
+        }
+    }
+    /// Renders the referenced template into lines for a [`ParametersLineToken::Reference`].
+    fn render_inline_reference(
+        &mut self,
+        name: &'t str,
+        is_optional: bool,
+        line_index: usize,
+        column: usize,
+        line_ending: LineEnding,
+        parameters: &dyn TemplateData,
+    ) -> Result<assemble::ReferenceLines, Error> {
+        let mut output = String::new();
+        let mut renderer = Renderer {
+            templates: self.templates,
+            options: self.options,
+            references: std::mem::take(&mut self.references),
+            path: std::mem::take(&mut self.path),
+            unused: std::mem::take(&mut self.unused),
+            buffer: String::new(),
+            pending_line_ending: None,
+            separator: match line_ending {
+                LineEnding::None => self.separator,
+                line_ending => line_ending,
+            },
+            result: &mut output,
+        };
+        let result = renderer
+            .render_reference(
+                name,
+                is_optional,
+                line_index,
+                column,
+                LineEnding::None,
+                parameters,
+                &None,
+                &None,
+            )
+            .and_then(|is_present| renderer.finish().map(|()| is_present));
+        self.references = renderer.references;
+        self.path = renderer.path;
+        self.unused = renderer.unused;
+        // Only what is rendered repeats or removes the line, an absent optional reference
+        // leaves the rest of it.
+        Ok(if result? || !is_optional {
+            assemble::ReferenceLines::new(output)
+        } else {
+            assemble::ReferenceLines::empty()
+        })
+    }
+    fn render_parameters_line(
+        &mut self,
+        tokens: &'t [ParametersLineToken],
+        line_index: usize,
+        line_ending: LineEnding,
+        parameters: &dyn TemplateData,
         external_left: &Option<String>,
         external_right: &Option<String>,
-        result: &mut String,
-    ) -> Result<(), String> {
-        for line in self.lines.iter() {
-            match line {
-                Line::Raw { value } => {
-                    if let Some(external_left) = external_left {
-                        result.push_str(external_left.as_str());
+    ) -> Result<(), Error> {
+        let all_tokens_are_optional = tokens.iter().all(|token| match token {
+            ParametersLineToken::Raw { .. } | ParametersLineToken::Reference { .. } => true,
+            ParametersLineToken::Parameter { is_optional, .. } => *is_optional,
+        });
+        let mut values = Vec::new();
+        let mut references = Vec::new();
+        for token in tokens {
+            match token {
+                ParametersLineToken::Raw { .. } => {}
+                ParametersLineToken::Parameter {
+                    is_optional,
+                    name,
+                    column,
+                    ..
+                } => values.push(match parameters.get(name) {
+                    Some(ValueRef::Value(value)) => LineValue::Value(value),
+                    Some(ValueRef::Values(values)) => {
+                        let mut values = values.peekable();
+                        if values.peek().is_none() {
+                            return Err(Error::EmptyValuesVec {
+                                name: name.to_string(),
+                                location: self.location(line_index, *column),
+                            });
+                        }
+                        LineValue::Values(values)
                     }
-                    result.push_str(value);
-                    if let Some(external_right) = external_right {
-                        result.push_str(external_right.as_str());
+                    Some(ValueRef::Data(_)) => {
+                        return Err(Error::ExpectedValue {
+                            name: name.to_string(),
+                            location: self.location(line_index, *column),
+                        });
                     }
-                    result.push('\n');
+                    // An empty vec has no element type, `[]` in JSON becomes an empty data vec.
+                    Some(ValueRef::DataIter(mut iter)) => {
+                        return Err(match iter.next() {
+                            None => Error::EmptyValuesVec {
+                                name: name.to_string(),
+                                location: self.location(line_index, *column),
+                            },
+                            Some(_) => Error::ExpectedValue {
+                                name: name.to_string(),
+                                location: self.location(line_index, *column),
+                            },
+                        });
+                    }
+                    None if !is_optional => {
+                        return Err(Error::MissingParameter {
+                            name: name.to_string(),
+                            location: self.location(line_index, *column),
+                        });
+                    }
+                    None => LineValue::Missing,
+                }),
+                ParametersLineToken::Reference {
+                    is_optional,
+                    name,
+                    column,
+                } => references.push(self.render_inline_reference(
+                    name,
+                    *is_optional,
+                    line_index,
+                    *column,
+                    line_ending,
+                    parameters,
+                )?),
+            }
+        }
+        // Lines with indented values or references are assembled first, as their text is
+        // repeated around each line of those.
+        let has_indent = tokens.iter().any(|token| {
+            matches!(
+                token,
+                ParametersLineToken::Parameter {
+                    is_indent: true,
+                    ..
                 }
-                Line::Parameters { tokens } => {
-                    let all_tokens_are_optional = tokens.iter().all(|token| match token {
-                        ParametersLineToken::Raw { value: _ } => true,
-                        ParametersLineToken::Parameter {
-                            is_optional,
-                            name: _,
-                        } => *is_optional,
-                    });
-                    let mut value_index = 0_i64;
-                    loop {
-                        let mut new_value_index = value_index + 1;
-                        if let Some(external_left) = external_left {
-                            result.push_str(external_left.as_str());
-                        }
-                        for token in tokens {
-                            match token {
-                                ParametersLineToken::Raw { value } => result.push_str(value),
-                                ParametersLineToken::Parameter { is_optional, name } => {
-                                    if let Some(value_variant) = parameters.get(*name) {
-                                        match value_variant {
-                                            TemplateParametersValue::Value(value) => {
-                                                if value_index == 0 {
-                                                    result.push_str(value);
-                                                }
-                                                if !is_optional || all_tokens_are_optional {
-                                                    new_value_index = -1;
-                                                }
-                                            }
-                                            TemplateParametersValue::ValuesVec(values) => {
-                                                if values.len() == 0 {
-                                                    return Err(format!(
-                                                        "Expected non-empty Vec of values for parameter \"{name}\""
-                                                    ));
-                                                } else if values.len()
-                                                    == (value_index + 1).try_into().map_err(|error| format!("Can not convert value index plus one: {error}"))?
-                                                {
-                                                    new_value_index = -1;
-                                                }
-                                                let value = &values[value_index as usize];
-                                                result.push_str(value);
-                                            }
-                                            _ => {
-                                                return Err(format!(
-                                                    "Expected value or non-empty Vec of values for parameter \"{name}\""
-                                                ));
-                                            }
-                                        }
-                                    } else {
-                                        if !is_optional {
-                                            return Err(format!(
-                                                "Expected key for parameter \"{name}\""
-                                            ));
-                                        }
-                                        new_value_index = -1;
-                                    }
+            )
+        });
+        let is_assembled = has_indent || !references.is_empty();
+        let mut line = String::new();
+        let mut indents = Vec::new();
+        let mut positions = Vec::new();
+        let mut is_first = true;
+        loop {
+            let mut repeat = !values.is_empty();
+            if !is_assembled {
+                self.start_line()?;
+            }
+            line.clear();
+            indents.clear();
+            positions.clear();
+            if let Some(external_left) = external_left {
+                self.push_line_part(is_assembled, &mut line, external_left)?;
+            }
+            let mut line_values = values.iter_mut();
+            for token in tokens {
+                let (is_raw, is_indent, value) = match token {
+                    ParametersLineToken::Raw { value } => {
+                        self.push_line_part(is_assembled, &mut line, value)?;
+                        continue;
+                    }
+                    ParametersLineToken::Reference { .. } => {
+                        positions.push(line.len());
+                        continue;
+                    }
+                    ParametersLineToken::Parameter {
+                        is_optional,
+                        is_raw,
+                        is_indent,
+                        ..
+                    } => (
+                        is_raw,
+                        is_indent,
+                        match line_values.next().expect("one value per parameter token") {
+                            LineValue::Missing => {
+                                repeat = false;
+                                None
+                            }
+                            LineValue::Value(value) => {
+                                if !is_optional || all_tokens_are_optional {
+                                    repeat = false;
                                 }
+                                is_first.then_some(value.reborrow())
                             }
+                            LineValue::Values(values) => {
+                                let value = values.next();
+                                if values.peek().is_none() {
+                                    repeat = false;
+                                }
+                                value
+                            }
+                        },
+                    ),
+                };
+                if let Some(value) = value {
+                    if is_assembled {
+                        let start = line.len();
+                        self.format_value(&mut line, &value, *is_raw)?;
+                        if *is_indent {
+                            indents.push(start..line.len());
                         }
-                        if let Some(external_right) = external_right {
-                            result.push_str(external_right.as_str());
-                        }
-                        result.push('\n');
-                        value_index = new_value_index;
-                        if value_index == -1 {
-                            break;
-                        }
+                    } else {
+                        self.push_value(&value, *is_raw)?;
+                    }
+                }
+            }
+            if let Some(external_right) = external_right {
+                self.push_line_part(is_assembled, &mut line, external_right)?;
+            }
+            if is_assembled {
+                assemble::push_assembled_line(
+                    self.result,
+                    &mut self.pending_line_ending,
+                    self.options.line_endings,
+                    &line,
+                    &positions,
+                    &indents,
+                    &references,
+                    line_ending,
+                    self.separator,
+                )?;
+            } else {
+                self.pending_line_ending = Some(line_ending);
+            }
+            if !repeat {
+                return Ok(());
+            }
+            is_first = false;
+        }
+    }
+    /// Renders the template `name` once per item of the parameter `name`, with `left` and
+    /// `right` added to the external ones around each of its lines. Returns whether both
+    /// the parameter and the template are present.
+    #[allow(clippy::too_many_arguments)]
+    fn render_reference(
+        &mut self,
+        name: &'t str,
+        is_optional: bool,
+        line_index: usize,
+        column: usize,
+        line_ending: LineEnding,
+        parameters: &dyn TemplateData,
+        left: &Option<String>,
+        right: &Option<String>,
+    ) -> Result<bool, Error> {
+        let options = self.options;
+        let Some(value) = parameters.get(name) else {
+            if options.strict_references && !is_optional {
+                return Err(Error::MissingParameter {
+                    name: name.to_string(),
+                    location: self.location(line_index, column),
+                });
+            }
+            return Ok(false);
+        };
+        let (subtemplate_parameters_iter, is_iter): (Box<dyn Iterator<Item = DataRef>>, bool) =
+            match value {
+                ValueRef::Data(subtemplate_parameters) => (
+                    Box::new(std::iter::once(DataRef::Borrowed(subtemplate_parameters))),
+                    false,
+                ),
+                ValueRef::DataIter(subtemplate_parameters_iter) => {
+                    (subtemplate_parameters_iter, true)
+                }
+                ValueRef::Value(_) | ValueRef::Values(_) => {
+                    return Err(Error::ExpectedParameters {
+                        name: name.to_string(),
+                        location: self.location(line_index, column),
+                    });
+                }
+            };
+        for (index, subtemplate_parameters) in subtemplate_parameters_iter.enumerate() {
+            if let Some(subtemplate) = self.templates.get(name) {
+                if options
+                    .max_depth
+                    .is_some_and(|max_depth| self.references.len() >= max_depth)
+                {
+                    return Err(Error::DepthLimit {
+                        name: name.to_string(),
+                        location: self.location(line_index, column),
+                    });
+                }
+                let path_length = self.path.len();
+                if options.unused_parameters != UnusedParameters::Ignore {
+                    if path_length > 0 {
+                        self.path.push('.');
+                    }
+                    self.path.push_str(name);
+                    if is_iter {
+                        self.path.push_str(&format!("[{index}]"));
                     }
                 }
+                self.references.push(name);
+                let separator = self.separator;
+                if line_ending != LineEnding::None {
+                    self.separator = line_ending;
+                }
+                self.render(subtemplate, &*subtemplate_parameters, left, right)?;
+                self.separator = separator;
+                self.references.pop();
+                // Unterminated last line of the referenced template ends like
+                // the reference line.
+                if self.pending_line_ending == Some(LineEnding::None) {
+                    self.pending_line_ending = Some(line_ending);
+                }
+                self.path.truncate(path_length);
+            } else if !is_optional {
+                return Err(Error::MissingTemplate {
+                    name: name.to_string(),
+                    location: self.location(line_index, column),
+                });
+            }
+        }
+        Ok(self.templates.contains_key(name))
+    }
+    fn render(
+        &mut self,
+        template: &'t Template,
+        parameters: &dyn TemplateData,
+        external_left: &Option<String>,
+        external_right: &Option<String>,
+    ) -> Result<(), Error> {
+        let options = self.options;
+        for (line_index, (line, line_ending)) in template
+            .lines
+            .iter()
+            .zip(template.line_endings.iter().copied())
+            .enumerate()
+        {
+            if self.references.is_empty() && line_index > 0 {
+                self.separator = template.line_endings[line_index - 1];
+            }
+            match line {
+                Line::Raw { value } => {
+                    self.start_line()?;
+                    if let Some(external_left) = external_left {
+                        self.result.push_str(external_left.as_str())?;
+                    }
+                    self.result.push_str(value)?;
+                    if let Some(external_right) = external_right {
+                        self.result.push_str(external_right.as_str())?;
+                    }
+                    self.pending_line_ending = Some(line_ending);
+                }
+                Line::Parameters { tokens } => self.render_parameters_line(
+                    tokens,
+                    line_index,
+                    line_ending,
+                    parameters,
+                    external_left,
+                    external_right,
+                )?,
                 Line::Reference {
                     left,
                     is_optional,
                     name,
+                    column,
                     right,
                 } => {
-                    if let Some(value_variant) = parameters.get(*name) {
-                        match value_variant {
-                            TemplateParametersValue::Parameters(subtemplate_parameters) => {
-                                if let Some(subtemplate) = templates.get(*name) {
-                                    subtemplate.render_internal(
-                                        subtemplate_parameters,
-                                        templates,
-                                        &Some(
-                                            external_left.clone().unwrap_or(String::new())
-                                                + left.unwrap_or(""),
-                                        ),
-                                        &Some(
-                                            external_right.clone().unwrap_or(String::new())
-                                                + right.unwrap_or(""),
-                                        ),
-                                        result,
-                                    )?;
-                                } else if !*is_optional {
-                                    return Err(format!(
-                                        "No template parameters provided for template reference \"{name}\""
-                                    ));
-                                }
-                            }
-                            TemplateParametersValue::ParametersVec(subtemplate_parameters_vec) => {
-                                for subtemplate_parameters in subtemplate_parameters_vec {
-                                    if let Some(subtemplate) = templates.get(*name) {
-                                        subtemplate.render_internal(
-                                            subtemplate_parameters,
-                                            templates,
-                                            &Some(
-                                                external_left.clone().unwrap_or(String::new())
-                                                    + left.unwrap_or(""),
-                                            ),
-                                            &Some(
-                                                external_right.clone().unwrap_or(String::new())
-                                                    + right.unwrap_or(""),
-                                            ),
-                                            result,
-                                        )?;
-                                    } else if !*is_optional {
-                                        return Err(format!(
-                                            "No template parameters provided for template reference \"{name}\""
-                                        ));
-                                    }
-                                }
-                            }
-                            _ => {
-                                return Err(format!(
-                                    "Expected template parameters of template parameters Vec for template reference \"{name}\""
-                                ));
-                            }
-                        }
-                    }
+                    self.render_reference(
+                        name,
+                        *is_optional,
+                        line_index,
+                        *column,
+                        line_ending,
+                        parameters,
+                        &Some(
+                            external_left.clone().unwrap_or_default()
+                                + left.as_deref().unwrap_or(""),
+                        ),
+                        &Some(
+                            external_right.clone().unwrap_or_default()
+                                + right.as_deref().unwrap_or(""),
+                        ),
+                    )?;
                 }
             }
         }
+        if options.unused_parameters != UnusedParameters::Ignore {
+            self.collect_unused(template, parameters);
+        }
         Ok(())
     }
+}
+
+impl Template<'_> {
+    /// Whether any tag of this template reads the parameter `name`.
+    fn uses(&self, name: &str) -> bool {
+        self.lines.iter().any(|line| match line {
+            Line::Raw { .. } => false,
+            Line::Parameters { tokens } => tokens.iter().any(|token| match token {
+                ParametersLineToken::Raw { .. } => false,
+                ParametersLineToken::Parameter {
+                    name: token_name, ..
+                }
+                | ParametersLineToken::Reference {
+                    name: token_name, ..
+                } => *token_name == name,
+            }),
+            Line::Reference {
+                name: line_name, ..
+            } => *line_name == name,
+        })
+    }
     pub fn render(
         &self,
-        parameters: &TemplateParameters,
+        parameters: &dyn TemplateData,
         templates: &Templates,
-    ) -> Result<String, String> {
+    ) -> Result<String, Error> {
+        self.render_with(parameters, templates, &RenderOptions::default())
+    }
+    pub fn render_with(
+        &self,
+        parameters: &dyn TemplateData,
+        templates: &Templates,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
         let mut result = String::new();
-        self.render_internal(parameters, templates, &None, &None, &mut result)?;
+        self.render_to(parameters, templates, options, &mut result)?;
         Ok(result)
     }
+    /// Like [`Template::render_with`], also returning paths of parameters no tag used,
+    /// e.g. `Row[3].Cell`, when [`RenderOptions::unused_parameters`] is [`UnusedParameters::Warn`].
+    pub fn render_with_warnings(
+        &self,
+        parameters: &dyn TemplateData,
+        templates: &Templates,
+        options: &RenderOptions,
+    ) -> Result<(String, Vec<String>), Error> {
+        let mut result = String::new();
+        let unused = self.render_to(parameters, templates, options, &mut result)?;
+        Ok((result, unused))
+    }
+    /// Renders into any [`Sink`], e.g. an [`IoSink`] that must then be flushed with [`IoSink::into_inner`].
+    ///
+    /// Returns paths of unused parameters when [`RenderOptions::unused_parameters`] is
+    /// [`UnusedParameters::Warn`]. They are only known at the end, so with
+    /// [`UnusedParameters::Error`] the sink has already received the whole output when the error is returned.
+    pub fn render_to(
+        &self,
+        parameters: &dyn TemplateData,
+        templates: &Templates,
+        options: &RenderOptions,
+        sink: &mut impl Sink,
+    ) -> Result<Vec<String>, Error> {
+        let mut renderer = Renderer {
+            templates,
+            options,
+            references: Vec::new(),
+            path: String::new(),
+            unused: Vec::new(),
+            buffer: String::new(),
+            pending_line_ending: None,
+            separator: LineEnding::Lf,
+            result: sink,
+        };
+        renderer.render(self, parameters, &None, &None)?;
+        renderer.finish()?;
+        match options.unused_parameters {
+            UnusedParameters::Error if !renderer.unused.is_empty() => {
+                Err(Error::UnusedParameters {
+                    paths: renderer.unused,
+                })
+            }
+            _ => Ok(renderer.unused),
+        }
+    }
+    pub fn render_to_writer(
+        &self,
+        parameters: &dyn TemplateData,
+        templates: &Templates,
+        writer: impl io::Write,
+    ) -> Result<(), Error> {
+        let mut sink = IoSink::new(writer);
+        self.render_to(parameters, templates, &RenderOptions::default(), &mut sink)?;
+        sink.into_inner()?;
+        Ok(())
+    }
+    pub fn render_to_fmt(
+        &self,
+        parameters: &dyn TemplateData,
+        templates: &Templates,
+        writer: impl fmt::Write,
+    ) -> Result<(), Error> {
+        self.render_to(
+            parameters,
+            templates,
+            &RenderOptions::default(),
+            &mut FmtSink::new(writer),
+        )?;
+        Ok(())
+    }
 }
 
 #[test]
@@ -365,32 +1650,55 @@ fn test_parse() {
 
     assert_eq!(template.lines.len(), 4);
 
-    assert_eq!(template.lines[0], Line::Raw { value: "<tr>" });
+    assert_eq!(
+        template.lines[0],
+        Line::Raw {
+            value: "<tr>".into()
+        }
+    );
     assert_eq!(
         template.lines[1],
         Line::Parameters {
             tokens: Vec::from([
-                ParametersLineToken::Raw { value: "    <td>" },
+                ParametersLineToken::Raw {
+                    value: "    <td>".into()
+                },
                 ParametersLineToken::Parameter {
                     is_optional: false,
-                    name: "cell1"
+                    is_raw: false,
+                    is_indent: false,
+                    name: "cell1".into(),
+                    column: 9
+                },
+                ParametersLineToken::Raw {
+                    value: "</td><td>".into()
                 },
-                ParametersLineToken::Raw { value: "</td><td>" },
                 ParametersLineToken::Parameter {
                     is_optional: true,
-                    name: "cell2"
+                    is_raw: false,
+                    is_indent: false,
+                    name: "cell2".into(),
+                    column: 39
                 },
-                ParametersLineToken::Raw { value: "</td>" }
+                ParametersLineToken::Raw {
+                    value: "</td>".into()
+                }
             ])
         }
     );
-    assert_eq!(template.lines[2], Line::Raw { value: "</tr>" });
+    assert_eq!(
+        template.lines[2],
+        Line::Raw {
+            value: "</tr>".into()
+        }
+    );
     assert_eq!(
         template.lines[3],
         Line::Reference {
             left: None,
             is_optional: false,
-            name: "Ref1",
+            name: "Ref1".into(),
+            column: 1,
             right: None
         }
     );
@@ -406,14 +1714,16 @@ macro_rules! test {
                 let parsed = parser.parse(template_text).unwrap();
                 parsed_templates.insert(template_name, parsed);
             }
+            let template = parser.parse($template_text).unwrap();
             assert_eq!(
-                parser
-                    .parse($template_text)
-                    .unwrap()
-                    .render($parameters, &parsed_templates,)
-                    .unwrap(),
+                template.render($parameters, &parsed_templates).unwrap(),
                 $correct_result
             );
+            let mut written = Vec::new();
+            template
+                .render_to_writer($parameters, &parsed_templates, &mut written)
+                .unwrap();
+            assert_eq!(String::from_utf8(written).unwrap(), $correct_result);
         }
     };
 }
@@ -422,61 +1732,674 @@ test!(
     "one <!-- (param)p --> two",
     &params! {"p" => tp_value!("lalala".to_string())},
     [],
-    "one lalala two\n"
+    "one lalala two"
 );
 test!(
     render_multivalued_param,
     "one <!-- (param)p --> two",
     &params! {"p" => tp_values! ["v1", "v2"]},
     [],
-    "one v1 two\none v2 two\n"
+    "one v1 two\none v2 two"
 );
 test!(
     render_multiple_params,
     "one <!-- (param)p1 --> <!-- (param)p2 --> two",
     &params! {"p1" => tp_value!("v1".to_string()), "p2" => tp_value!("v2".to_string())},
     [],
-    "one v1 v2 two\n"
+    "one v1 v2 two"
 );
 test!(
     render_optional_param,
     "one <!-- (optional)(param)p --> two",
-    &TemplateParameters::from([]),
+    &TemplateParameters::new(),
     [],
-    "one  two\n"
+    "one  two"
 );
 test!(
     render_optional_param_while_there_is_also_param_with_more_than_one_value,
     "left <!-- (param)p1 --> middle <!-- (optional)(param)p2 --> right\nplain text",
     &params! {"p1" => tp_values!("lalala", "lululu"), "p2" => tp_value!("lololo".to_string())},
     [],
-    "left lalala middle lololo right\nleft lululu middle  right\nplain text\n"
+    "left lalala middle lololo right\nleft lululu middle  right\nplain text"
 );
 test!(
     render_ref,
     "one <!-- (ref)r --> two",
     &params! {"r" => tp_params! {"p" => tp_value!("v".to_string())}},
     [("r", "three")],
-    "one three two\n"
+    "one three two"
 );
 test!(
     render_2x2_html_table,
     "<table>\n    <!-- (ref)Row -->\n</table>",
     &params! {"Row" => tp_params_vec!(params! {"cell" => tp_values!("1.1", "2.1")}, params! {"cell" => tp_values!("1.2", "2.2")})},
     [("Row", "<tr>\n    <td><!-- (param)cell --></td>\n</tr>")],
-    "<table>\n    <tr>\n        <td>1.1</td>\n        <td>2.1</td>\n    </tr>\n    <tr>\n        <td>1.2</td>\n        <td>2.2</td>\n    </tr>\n</table>\n"
+    "<table>\n    <tr>\n        <td>1.1</td>\n        <td>2.1</td>\n    </tr>\n    <tr>\n        <td>1.2</td>\n        <td>2.2</td>\n    </tr>\n</table>"
 );
 test!(
     render_ref_with_param,
     "one <!-- (ref)r --> two",
     &params! {"r" => tp_params! {"p" => tp_value!("three".to_string())}},
     [("r", "<!-- (param)p -->")],
-    "one three two\n"
+    "one three two"
 );
 test!(
     render_multivalued_ref_with_param,
     "one <!-- (ref)r --> two",
     &params! {"r" => tp_params_vec!(params! {"p" => tp_value!("three".to_string())}, params! {"p" => tp_value!("four".to_string())})},
     [("r", "<!-- (param)p -->")],
-    "one three two\none four two\n"
+    "one three two\none four two"
+);
+
+#[test]
+fn test_render_error_location() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([
+        (
+            "Row",
+            parser.parse("<tr>\n    <!-- (ref)Cell -->\n</tr>").unwrap(),
+        ),
+        (
+            "Cell",
+            parser.parse("<td><!-- (param)value --></td>").unwrap(),
+        ),
+    ]);
+    let error = parser
+        .parse("<table>\n    <!-- (ref)Row -->\n</table>")
+        .unwrap()
+        .render(
+            &params! {"Row" => tp_params! {"Cell" => tp_params! {}}},
+            &templates,
+        )
+        .unwrap_err();
+    match &error {
+        Error::MissingParameter { name, location } => {
+            assert_eq!(name, "value");
+            assert_eq!(location.template(), Some("Cell"));
+            assert_eq!(location.references, vec!["Row", "Cell"]);
+            assert_eq!(location.line, 1);
+            assert_eq!(location.column, 5);
+        }
+        _ => panic!("unexpected error {error:?}"),
+    }
+    assert_eq!(
+        error.to_string(),
+        "Expected key for parameter \"value\" at template \"Cell\", line 1, column 5 (referenced via Row -> Cell)"
+    );
+}
+#[test]
+fn test_render_error_kinds() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let template = parser.parse("<!-- (param)p -->\n<!-- (ref)r -->").unwrap();
+    let render = |parameters: &TemplateParameters| template.render(parameters, &Templates::new());
+    assert!(matches!(
+        render(&params! {"p" => tp_params! {}}),
+        Err(Error::ExpectedValue { .. })
+    ));
+    assert!(matches!(
+        render(&params! {"p" => tp_values!()}),
+        Err(Error::EmptyValuesVec { .. })
+    ));
+    assert!(matches!(
+        render(&params! {"p" => tp_value!("v".to_string()), "r" => tp_value!("v".to_string())}),
+        Err(Error::ExpectedParameters { .. })
+    ));
+    assert!(matches!(
+        render(&params! {"p" => tp_value!("v".to_string()), "r" => tp_params! {}}),
+        Err(Error::MissingTemplate {
+            location: Location {
+                line: 2,
+                column: 1,
+                ..
+            },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_render_to_fmt_and_failing_writer() {
+    struct FailingWriter;
+    impl io::Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk is full"))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let template = parser.parse("one <!-- (param)p --> two").unwrap();
+    let parameters = params! {"p" => tp_values!("v1", "v2")};
+    let mut formatted = String::new();
+    template
+        .render_to_fmt(&parameters, &Templates::new(), &mut formatted)
+        .unwrap();
+    assert_eq!(formatted, "one v1 two\none v2 two");
+    assert!(matches!(
+        template.render_to_writer(&parameters, &Templates::new(), FailingWriter),
+        Err(Error::Io(_))
+    ));
+}
+test!(
+    render_escaped_param,
+    "one <!-- (escape)(param)p --> <!-- (param)p --> two",
+    &params! {"p" => tp_value!("v".to_string())},
+    [],
+    "one <!-- (param)p --> v two"
+);
+test!(
+    render_escaped_ref,
+    "<!-- (escape)(optional)(ref)r -->",
+    &params! {"r" => tp_params! {}},
+    [("r", "three")],
+    "<!-- (optional)(ref)r -->"
+);
+test!(
+    render_escaped_escape,
+    "<!--(escape)(escape)(param)p-->",
+    &TemplateParameters::new(),
+    [],
+    "<!--(escape)(param)p-->"
+);
+test!(
+    render_ref_with_escaped_param_on_line,
+    "<!-- (escape)(param)p --> <!-- (ref)r -->",
+    &params! {"r" => tp_params! {}},
+    [("r", "three")],
+    "<!-- (param)p --> three"
 );
+#[test]
+fn test_custom_escape_operator() {
+    let syntax = Syntax {
+        open_tag: "{{",
+        close_tag: "}}",
+        escape_operator: "literal",
+        ..Syntax::default()
+    };
+    let parser = Parser::from_syntax(&syntax, "param", "ref").unwrap();
+    assert_eq!(
+        parser
+            .parse("{{ (literal)(param)p }} {{ (escape)(param)p }}")
+            .unwrap()
+            .render(
+                &params! {"p" => tp_value!("v".to_string())},
+                &Templates::new()
+            )
+            .unwrap(),
+        "{{ (param)p }} {{ (escape)(param)p }}"
+    );
+}
+#[test]
+fn test_render_with_escaper() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([(
+        "Row",
+        parser
+            .parse("<td title=\"<!-- (param)title -->\"><!-- (optional)(raw)(param)html --></td>")
+            .unwrap(),
+    )]);
+    let template = parser
+        .parse("<p><!-- (param)text --></p><!-- (raw)(optional)(param)html -->\n<!-- (ref)Row -->")
+        .unwrap();
+    let parameters = params! {
+        "text" => tp_value!("<script>alert('x')</script>".to_string()),
+        "html" => tp_value!("<br>".to_string()),
+        "Row" => tp_params! {
+            "title" => tp_value!("\"quoted\" & more".to_string()),
+            "html" => tp_value!("<b>bold</b>".to_string())
+        }
+    };
+    assert_eq!(
+        template
+            .render_with(
+                &parameters,
+                &templates,
+                &RenderOptions {
+                    escaper: &HtmlEscaper,
+                    ..RenderOptions::default()
+                }
+            )
+            .unwrap(),
+        "<p>&lt;script&gt;alert(&#x27;x&#x27;)&lt;/script&gt;</p><br>\n<td title=\"&quot;quoted&quot; &amp; more\"><b>bold</b></td>"
+    );
+    assert_eq!(
+        parser
+            .parse("echo <!-- (param)args -->")
+            .unwrap()
+            .render_with(
+                &params! {"args" => tp_values!("it's", "$HOME")},
+                &Templates::new(),
+                &RenderOptions {
+                    escaper: &ShellEscaper,
+                    ..RenderOptions::default()
+                }
+            )
+            .unwrap(),
+        "echo 'it'\\''s'\necho '$HOME'"
+    );
+}
+#[test]
+fn test_max_depth() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([(
+        "Menu",
+        parser
+            .parse("<li><!-- (param)title --></li>\n<!-- (optional)(ref)Menu -->")
+            .unwrap(),
+    )]);
+    let template = parser.parse("<!-- (ref)Menu -->").unwrap();
+    let parameters = params! {"Menu" => tp_params! {
+        "title" => tp_value!("1".to_string()),
+        "Menu" => tp_params! {
+            "title" => tp_value!("1.1".to_string()),
+            "Menu" => tp_params! {"title" => tp_value!("1.1.1".to_string())}
+        }
+    }};
+    let with_max_depth = |max_depth| RenderOptions {
+        max_depth: Some(max_depth),
+        ..RenderOptions::default()
+    };
+    assert_eq!(
+        template
+            .render_with(&parameters, &templates, &with_max_depth(3))
+            .unwrap(),
+        "<li>1</li>\n<li>1.1</li>\n<li>1.1.1</li>\n"
+    );
+    assert!(matches!(
+        template.render_with(&parameters, &templates, &with_max_depth(2)),
+        Err(Error::DepthLimit {
+            location: Location { line: 2, .. },
+            ..
+        })
+    ));
+}
+test!(
+    render_missing_ref_key_leniently,
+    "one\n<!-- (ref)r -->\ntwo",
+    &TemplateParameters::new(),
+    [("r", "three")],
+    "one\ntwo"
+);
+#[test]
+fn test_strict_references() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([("r", parser.parse("three").unwrap())]);
+    let options = RenderOptions {
+        strict_references: true,
+        ..RenderOptions::default()
+    };
+    assert!(matches!(
+        parser.parse("one\n<!-- (ref)r -->").unwrap().render_with(
+            &TemplateParameters::new(),
+            &templates,
+            &options
+        ),
+        Err(Error::MissingParameter {
+            location: Location {
+                line: 2,
+                column: 1,
+                ..
+            },
+            ..
+        })
+    ));
+    assert_eq!(
+        parser
+            .parse("one\n<!-- (optional)(ref)r -->")
+            .unwrap()
+            .render_with(&TemplateParameters::new(), &templates, &options)
+            .unwrap(),
+        "one\n"
+    );
+}
+
+#[test]
+fn test_unused_parameters() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([(
+        "Row",
+        parser.parse("<tr><!-- (param)cell --></tr>").unwrap(),
+    )]);
+    let template = parser
+        .parse("<table>\n<!-- (ref)Row -->\n</table><!-- (optional)(param)caption -->")
+        .unwrap();
+    let parameters = params! {
+        "Row" => tp_params_vec!(
+            params! {"cell" => tp_value!("1".to_string())},
+            params! {"cell" => tp_value!("2".to_string()), "Cell" => tp_value!("3".to_string())}
+        ),
+        "Rows" => tp_params! {}
+    };
+    let warn = RenderOptions {
+        unused_parameters: UnusedParameters::Warn,
+        ..RenderOptions::default()
+    };
+    assert_eq!(
+        template
+            .render_with_warnings(&parameters, &templates, &warn)
+            .unwrap(),
+        (
+            "<table>\n<tr>1</tr>\n<tr>2</tr>\n</table>".to_string(),
+            vec!["Row[1].Cell".to_string(), "Rows".to_string()]
+        )
+    );
+    assert_eq!(
+        template
+            .render_with_warnings(&parameters, &templates, &RenderOptions::default())
+            .unwrap()
+            .1,
+        Vec::<String>::new()
+    );
+    let error = template
+        .render_with(
+            &parameters,
+            &templates,
+            &RenderOptions {
+                unused_parameters: UnusedParameters::Error,
+                ..RenderOptions::default()
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Parameters not used by any template: \"Row[1].Cell\", \"Rows\""
+    );
+}
+
+#[test]
+fn test_render_scalars() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let template = parser
+        .parse("<!-- (param)int --> <!-- (param)flag --> <!-- (param)address --> <!-- (raw)(param)shell -->\n<!-- (param)number -->")
+        .unwrap();
+    let text = String::from("a'b");
+    let parameters = params! {
+        "int" => tp_value!(-3),
+        "number" => tp_values!(0.5, 2_u8),
+        "flag" => tp_value!(true),
+        "address" => TemplateParametersValue::Value(Scalar::display("x y")),
+        "shell" => tp_value!(&text)
+    };
+    assert_eq!(
+        template.render(&parameters, &Templates::new()).unwrap(),
+        "-3 true x y a'b\n0.5\n2"
+    );
+    assert_eq!(
+        template
+            .render_with(
+                &parameters,
+                &Templates::new(),
+                &RenderOptions {
+                    escaper: &ShellEscaper,
+                    ..RenderOptions::default()
+                }
+            )
+            .unwrap(),
+        "'-3' 'true' 'x y' a'b\n'0.5'\n'2'"
+    );
+}
+
+#[test]
+fn test_owned_parameter_names() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([(
+        "Row",
+        parser.parse("<tr><!-- (param)cell --></tr>").unwrap(),
+    )]);
+    let template = parser
+        .parse("<table>\n<!-- (ref)Row -->\n</table><!-- (param)caption -->")
+        .unwrap();
+    let names = ["caption".to_string(), "cell".to_string()];
+    let row = HashMap::from([(names[1].clone(), tp_values!(1, 2))]);
+    let parameters = params! {
+        names[0].clone() => tp_value!("c"),
+        "Row" => TemplateParametersValue::from(row)
+    };
+    assert_eq!(
+        template.render(&parameters, &templates).unwrap(),
+        "<table>\n<tr>1</tr>\n<tr>2</tr>\n</table>c"
+    );
+    let parameters = TemplateParameters::from(BTreeMap::from([
+        ("caption".to_string(), tp_value!("d")),
+        (
+            "Row".to_string(),
+            tp_params_vec!(TemplateParameters::from([(
+                Cow::Borrowed("cell"),
+                tp_value!(3)
+            )])),
+        ),
+    ]));
+    assert_eq!(
+        template.render(&parameters, &templates).unwrap(),
+        "<table>\n<tr>3</tr>\n</table>d"
+    );
+}
+
+#[test]
+fn test_line_endings() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let template = parser
+        .parse("@echo off\r\n<!-- (ref)Echo -->\r\nexit\r\n")
+        .unwrap();
+    assert_eq!(
+        template.line_endings(),
+        [LineEnding::CrLf, LineEnding::CrLf, LineEnding::CrLf]
+    );
+    let templates = Templates::from([("Echo", parser.parse("echo <!-- (param)line -->").unwrap())]);
+    let parameters = params! {
+        "Echo" => tp_params_vec!(params! {"line" => tp_values!("a", "b")}, params! {"line" => tp_value!("c")})
+    };
+    assert_eq!(
+        template.render(&parameters, &templates).unwrap(),
+        "@echo off\r\necho a\r\necho b\r\necho c\r\nexit\r\n"
+    );
+    // Repeated unterminated lines are separated like the reference line, or like the
+    // previous line in the root template.
+    assert_eq!(
+        parser
+            .parse("a\r\nx <!-- (param)v -->")
+            .unwrap()
+            .render(&params! {"v" => tp_values!("1", "2")}, &templates)
+            .unwrap(),
+        "a\r\nx 1\r\nx 2"
+    );
+    assert_eq!(
+        parser
+            .parse("<!-- (ref)Echo -->\r\n")
+            .unwrap()
+            .render(
+                &params! {"Echo" => tp_params! {"line" => tp_values!("a", "b")}},
+                &templates
+            )
+            .unwrap(),
+        "echo a\r\necho b\r\n"
+    );
+    let render = |line_endings| {
+        template
+            .render_with(
+                &parameters,
+                &templates,
+                &RenderOptions {
+                    line_endings,
+                    ..RenderOptions::default()
+                },
+            )
+            .unwrap()
+    };
+    assert_eq!(
+        render(LineEndings::Lf),
+        "@echo off\necho a\necho b\necho c\nexit\n"
+    );
+    assert_eq!(
+        render(LineEndings::CrLf),
+        "@echo off\r\necho a\r\necho b\r\necho c\r\nexit\r\n"
+    );
+    assert_eq!(
+        parser.parse("a\nb\n\n").unwrap().line_endings(),
+        [LineEnding::Lf, LineEnding::Lf, LineEnding::Lf]
+    );
+    assert!(parser.parse("").unwrap().lines().is_empty());
+}
+
+#[test]
+fn test_indent() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([(
+        "Job",
+        parser
+            .parse("script: |\n  <!-- (indent)(param)script -->\nname: <!-- (param)script -->")
+            .unwrap(),
+    )]);
+    let template = parser.parse("jobs:\n  - <!-- (ref)Job -->\n").unwrap();
+    assert_eq!(
+        template
+            .render(
+                &params! {"Job" => tp_params! {"script" => tp_value!("echo a\necho b\n")}},
+                &templates
+            )
+            .unwrap(),
+        "jobs:\n  - script: |\n  -   echo a\n  -   echo b\n  - name: echo a\necho b\n\n"
+    );
+    let template = parser
+        .parse("[<!-- (indent)(param)x -->], <!-- (param)y -->")
+        .unwrap();
+    assert_eq!(
+        template
+            .render_with(
+                &params! {"x" => tp_values!("a\r\nb", "c"), "y" => tp_value!(1)},
+                &Templates::new(),
+                &RenderOptions {
+                    line_endings: LineEndings::Lf,
+                    ..RenderOptions::default()
+                }
+            )
+            .unwrap(),
+        "[a], 1\n[b], 1"
+    );
+}
+
+#[test]
+fn test_inline_references() {
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([
+        ("Icon", parser.parse("<i><!-- (param)name --></i>").unwrap()),
+        ("Lines", parser.parse("<!-- (param)line -->\r\n").unwrap()),
+    ]);
+    let template = parser
+        .parse("<li><!-- (ref)Icon --> <!-- (param)label --></li>\n[<!-- (optional)(ref)Icon -->|<!-- (ref)Lines -->]")
+        .unwrap();
+    assert_eq!(
+        template.references().collect::<Vec<_>>(),
+        [
+            ReferenceTag {
+                name: "Icon",
+                is_optional: false,
+                line: 1,
+                column: 5
+            },
+            ReferenceTag {
+                name: "Icon",
+                is_optional: true,
+                line: 2,
+                column: 2
+            },
+            ReferenceTag {
+                name: "Lines",
+                is_optional: false,
+                line: 2,
+                column: 31
+            }
+        ]
+    );
+    assert_eq!(
+        template
+            .render(
+                &params! {
+                    "Icon" => tp_params_vec!(params! {"name" => tp_value!("x")}, params! {"name" => tp_value!("y")}),
+                    "label" => tp_values!("a", "b"),
+                    "Lines" => tp_params! {"line" => tp_values!(1, 2)}
+                },
+                &templates
+            )
+            .unwrap(),
+        "<li><i>x</i> a</li>\n<li><i>y</i> a</li>\n<li><i>x</i> b</li>\n<li><i>y</i> b</li>\n\
+         [<i>x</i>|1]\r\n[<i>x</i>|2]\r\n[<i>y</i>|1]\r\n[<i>y</i>|2]\r\n"
+    );
+    // Absent optional references render as empty text instead of removing the line.
+    let item = parser
+        .parse("<li><!-- (optional)(ref)Icon --> <!-- (param)label --></li>")
+        .unwrap();
+    assert_eq!(
+        item.render(&params! {"label" => tp_value!("Home")}, &templates)
+            .unwrap(),
+        "<li> Home</li>"
+    );
+    assert_eq!(
+        item.render(
+            &params! {"Icon" => tp_params! {}, "label" => tp_value!("Home")},
+            &Templates::new()
+        )
+        .unwrap(),
+        "<li> Home</li>"
+    );
+    assert_eq!(
+        item.render(
+            &params! {"Icon" => tp_params_vec!(), "label" => tp_value!("Home")},
+            &templates
+        )
+        .unwrap(),
+        ""
+    );
+    let warn = RenderOptions {
+        unused_parameters: UnusedParameters::Warn,
+        ..RenderOptions::default()
+    };
+    assert_eq!(
+        template
+            .render_with_warnings(
+                &params! {
+                    "Icon" => tp_params! {"name" => tp_value!("x"), "title" => tp_value!("t")},
+                    "label" => tp_value!("a")
+                },
+                &templates,
+                &warn
+            )
+            .unwrap(),
+        (
+            "<li><i>x</i> a</li>\n".to_string(),
+            vec!["Icon.title".to_string()]
+        )
+    );
+    assert!(matches!(
+        parser
+            .parse("<!-- (ref)Icon --><!-- (ref)Icon -->")
+            .unwrap()
+            .render(&params! {"Icon" => tp_params! {}}, &templates),
+        Err(Error::MissingParameter { name, location }) if name == "name"
+            && location == Location { references: vec!["Icon".to_string()], line: 1, column: 4 }
+    ));
+}
+
+#[test]
+fn test_owned_template() {
+    fn assert_static_send_sync<T: Clone + Send + Sync + 'static>(_: &T) {}
+    fn load(parser: &Parser, source: String) -> Template<'static> {
+        parser.parse_owned(source).unwrap()
+    }
+    let parser = Parser::from_syntax(&Syntax::default(), "param", "ref").unwrap();
+    let source = "<ul>\n  <!-- (ref)Item -->\n</ul><!-- (escape)(param)x -->".to_string();
+    let template = load(&parser, source.clone());
+    assert_static_send_sync(&template);
+    assert_eq!(template, parser.parse(&source).unwrap());
+    let templates: Templates<'static> = Templates::from([(
+        "Item",
+        load(&parser, "<li><!-- (param)name --></li>".to_string()),
+    )]);
+    let parameters = params! {"Item" => tp_params_vec!(params! {"name" => tp_value!("a")})};
+    let rendered = std::thread::spawn(move || template.clone().render(&parameters, &templates))
+        .join()
+        .unwrap()
+        .unwrap();
+    assert_eq!(rendered, "<ul>\n  <li>a</li>\n</ul><!-- (param)x -->");
+}
@@ -0,0 +1,238 @@
+use clap::{Parser as ArgumentsParser, ValueEnum};
+use drunk_snail::*;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DataFormat {
+    Json,
+    Yaml,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Escaping {
+    None,
+    Html,
+    XmlAttribute,
+    JsonString,
+    Shell,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Unused {
+    Ignore,
+    Warn,
+    Error,
+}
+
+/// Render a drunk_snail template with data from a JSON or YAML file.
+///
+/// Exit codes: 2 for invalid arguments, 3 for template errors,
+/// 4 for data errors and 5 for I/O errors.
+#[derive(Debug, ArgumentsParser)]
+#[command(version)]
+struct Arguments {
+    /// Root template file
+    template: PathBuf,
+    /// Directory with referenced templates, searched recursively, each named by its file stem
+    #[arg(short, long)]
+    templates: Option<PathBuf>,
+    /// Extension of files in the templates directory that are templates, all files if omitted
+    #[arg(short, long = "extension")]
+    extensions: Vec<String>,
+    /// Data file, standard input if omitted or "-"
+    #[arg(short, long)]
+    data: Option<PathBuf>,
+    /// Data format, guessed from the data file extension if omitted (JSON for standard input)
+    #[arg(short, long)]
+    format: Option<DataFormat>,
+    /// Output file, standard output if omitted
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    #[arg(long, default_value = "<!--")]
+    open_tag: String,
+    #[arg(long, default_value = "-->")]
+    close_tag: String,
+    #[arg(long, default_value = "optional")]
+    optional_operator: String,
+    #[arg(long, default_value = "escape")]
+    escape_operator: String,
+    #[arg(long, default_value = "raw")]
+    raw_operator: String,
+    #[arg(long, default_value = "indent")]
+    indent_operator: String,
+    #[arg(long, default_value = "param")]
+    parameter_operator: String,
+    #[arg(long, default_value = "ref")]
+    reference_operator: String,
+    /// Escaping applied to parameter values
+    #[arg(long, value_enum, default_value_t = Escaping::None)]
+    escape: Escaping,
+    /// Fail when data lacks the key of a non-optional reference
+    #[arg(long)]
+    strict: bool,
+    /// What to do with data keys no template uses: warnings go to standard error
+    #[arg(long, value_enum, default_value_t = Unused::Ignore)]
+    unused_parameters: Unused,
+    /// Write JSON Schema of the expected data instead of rendering
+    #[arg(long)]
+    json_schema: bool,
+}
+
+enum Failure {
+    Template(String),
+    Data(String),
+    Io(String),
+}
+impl Failure {
+    fn io(path: &Path) -> impl FnOnce(io::Error) -> Failure {
+        move |error| Failure::Io(format!("Can not access \"{}\": {error}", path.display()))
+    }
+}
+impl From<Error> for Failure {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(_) | Error::Fmt(_) => Failure::Io(error.to_string()),
+            Error::ReadTemplate { .. } => Failure::Io(error.to_string()),
+            Error::MissingTemplate { .. }
+            | Error::DuplicateTemplate { .. }
+            | Error::TemplateName { .. } => Failure::Template(error.to_string()),
+            _ => Failure::Data(error.to_string()),
+        }
+    }
+}
+
+fn read_data(arguments: &Arguments) -> Result<serde_json::Value, Failure> {
+    let (text, format) = match &arguments.data {
+        Some(path) if path.as_os_str() != "-" => (
+            fs::read_to_string(path).map_err(Failure::io(path))?,
+            arguments.format.unwrap_or(
+                match path.extension().and_then(|extension| extension.to_str()) {
+                    Some("yaml" | "yml") => DataFormat::Yaml,
+                    _ => DataFormat::Json,
+                },
+            ),
+        ),
+        _ => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map_err(Failure::io(Path::new("<stdin>")))?;
+            (text, arguments.format.unwrap_or(DataFormat::Json))
+        }
+    };
+    match format {
+        DataFormat::Json => serde_json::from_str(&text)
+            .map_err(|error| Failure::Data(format!("Can not parse JSON data: {error}"))),
+        DataFormat::Yaml => serde_yaml::from_str(&text)
+            .map_err(|error| Failure::Data(format!("Can not parse YAML data: {error}"))),
+    }
+}
+
+fn output(arguments: &Arguments) -> Result<Box<dyn Write>, Failure> {
+    Ok(match &arguments.output {
+        Some(path) => Box::new(fs::File::create(path).map_err(Failure::io(path))?),
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+fn run(arguments: &Arguments) -> Result<(), Failure> {
+    let parser = Parser::from_syntax(
+        &Syntax {
+            open_tag: &arguments.open_tag,
+            close_tag: &arguments.close_tag,
+            optional_operator: &arguments.optional_operator,
+            escape_operator: &arguments.escape_operator,
+            raw_operator: &arguments.raw_operator,
+            indent_operator: &arguments.indent_operator,
+        },
+        &arguments.parameter_operator,
+        &arguments.reference_operator,
+    )
+    .map_err(|error| Failure::Template(error.to_string()))?;
+
+    let template_text =
+        fs::read_to_string(&arguments.template).map_err(Failure::io(&arguments.template))?;
+    let directory = match &arguments.templates {
+        Some(directory) => TemplateDirectory::load(
+            directory,
+            &parser,
+            &DirectoryOptions {
+                extensions: &arguments
+                    .extensions
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>(),
+                ..DirectoryOptions::default()
+            },
+        )?,
+        None => TemplateDirectory::default(),
+    };
+    let template = parser
+        .parse(&template_text)
+        .map_err(|error| Failure::Template(error.to_string()))?;
+    let templates = directory.templates();
+
+    if arguments.json_schema {
+        let mut writer = output(arguments)?;
+        serde_json::to_writer_pretty(&mut writer, &template.schema(&templates).to_json_schema())
+            .map_err(|error| Failure::Io(format!("Can not write JSON Schema: {error}")))?;
+        return writer
+            .write_all(b"\n")
+            .map_err(Failure::io(Path::new("<output>")));
+    }
+
+    let data = read_data(arguments)?;
+    let parameters = parameters_from_json(&data)?;
+    let options = RenderOptions {
+        escaper: match arguments.escape {
+            Escaping::None => &NoEscaper,
+            Escaping::Html => &HtmlEscaper,
+            Escaping::XmlAttribute => &XmlAttributeEscaper,
+            Escaping::JsonString => &JsonStringEscaper,
+            Escaping::Shell => &ShellEscaper,
+        },
+        strict_references: arguments.strict,
+        unused_parameters: match arguments.unused_parameters {
+            Unused::Ignore => UnusedParameters::Ignore,
+            Unused::Warn => UnusedParameters::Warn,
+            Unused::Error => UnusedParameters::Error,
+        },
+        ..RenderOptions::default()
+    };
+    // Rendered in memory first, so that a failed run writes nothing, leaving previous output
+    // untouched.
+    let (rendered, unused) = template.render_with_warnings(&parameters, &templates, &options)?;
+    match &arguments.output {
+        Some(path) => fs::write(path, rendered).map_err(Failure::io(path))?,
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout
+                .write_all(rendered.as_bytes())
+                .and_then(|()| stdout.flush())
+                .map_err(Failure::io(Path::new("<output>")))?;
+        }
+    }
+    for path in unused {
+        eprintln!("Parameter \"{path}\" is not used");
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let arguments = Arguments::parse();
+    match run(&arguments) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            let (message, code) = match failure {
+                Failure::Template(message) => (message, 3),
+                Failure::Data(message) => (message, 4),
+                Failure::Io(message) => (message, 5),
+            };
+            eprintln!("{message}");
+            ExitCode::from(code)
+        }
+    }
+}
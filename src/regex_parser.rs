@@ -0,0 +1,243 @@
+//! Regex based parser kept behind the `regex` feature for comparison with [`Parser`](crate::Parser).
+//!
+//! It produces exactly the same [`Template`] as [`Parser`](crate::Parser) does for the same
+//! syntax, but finds tags with a single regex matched repeatedly along every line.
+
+use crate::{Error, Line, LineEnding, ParametersLineToken, Parser, Syntax, Template, column};
+use regex::Regex;
+
+#[derive(Debug)]
+pub struct RegexParser {
+    tag_regex: Regex,
+    optional_operator: String,
+    raw_operator: String,
+    indent_operator: String,
+}
+
+/// Alternation of every ordering of every subset of `operators`, as each modifier can be
+/// given once in any order.
+fn modifiers_pattern(operators: &[String]) -> String {
+    fn orderings(operators: &[String], used: &mut Vec<usize>, result: &mut Vec<String>) {
+        for index in 0..operators.len() {
+            if used.contains(&index) {
+                continue;
+            }
+            used.push(index);
+            result.push(
+                used.iter()
+                    .map(|index| operators[*index].as_str())
+                    .collect(),
+            );
+            orderings(operators, used, result);
+            used.pop();
+        }
+    }
+    let mut alternatives = Vec::new();
+    orderings(operators, &mut Vec::new(), &mut alternatives);
+    alternatives.push(String::new());
+    format!("(?:{})", alternatives.join("|"))
+}
+
+impl RegexParser {
+    pub fn from_syntax(
+        syntax: &Syntax,
+        parameter_operator: &str,
+        reference_operator: &str,
+    ) -> Result<Self, Error> {
+        let operator = |operator: &str| format!("({operator})");
+        let (optional_operator, raw_operator, indent_operator) = (
+            operator(syntax.optional_operator),
+            operator(syntax.raw_operator),
+            operator(syntax.indent_operator),
+        );
+        let modifiers = modifiers_pattern(&[
+            regex::escape(&optional_operator),
+            regex::escape(&raw_operator),
+            regex::escape(&indent_operator),
+        ]);
+        let escape = regex::escape(&operator(syntax.escape_operator));
+        // Capturing only outside of escaped tags, as group names must be unique.
+        let body = |capture: bool| {
+            let group = |name: &str| {
+                if capture {
+                    format!("?P<{name}>")
+                } else {
+                    "?:".to_string()
+                }
+            };
+            format!(
+                r"\((?:{}\)({}\w+)|{}\)({}\w+(?:[./]\w+)*)) *{}",
+                regex::escape(parameter_operator),
+                group("name"),
+                regex::escape(reference_operator),
+                group("reference"),
+                regex::escape(syntax.close_tag)
+            )
+        };
+        Ok(RegexParser {
+            // Escaped tags come first, as the parser tries them first at every open tag.
+            tag_regex: Regex::new(&format!(
+                r"{} *(?:(?P<escape>{escape})(?:{escape})*{modifiers}{}|(?P<modifiers>{modifiers}){})",
+                regex::escape(syntax.open_tag),
+                body(false),
+                body(true),
+            ))
+            .map_err(Error::TagRegex)?,
+            optional_operator,
+            raw_operator,
+            indent_operator,
+        })
+    }
+    fn parse_line<'a>(&self, line: &'a str) -> Line<'a> {
+        let mut tokens: Vec<ParametersLineToken> = Vec::new();
+        let mut has_parameters = false;
+        let mut escapes = Vec::new();
+        let mut references = Vec::new();
+        let mut raw_start = 0;
+        for capture in self.tag_regex.captures_iter(line) {
+            let tag = capture.get_match();
+            if let Some(escape) = capture.name("escape") {
+                tokens.push(ParametersLineToken::Raw {
+                    value: line[raw_start..escape.start()].into(),
+                });
+                escapes.push(escape.range());
+                raw_start = escape.end();
+                continue;
+            }
+            if tag.start() > raw_start {
+                tokens.push(ParametersLineToken::Raw {
+                    value: line[raw_start..tag.start()].into(),
+                });
+            }
+            let modifiers = capture
+                .name("modifiers")
+                .map_or("", |modifiers| modifiers.as_str());
+            let is_optional = modifiers.contains(&self.optional_operator);
+            let column = column(line, tag.start());
+            tokens.push(match capture.name("name") {
+                Some(name) => {
+                    has_parameters = true;
+                    ParametersLineToken::Parameter {
+                        is_optional,
+                        is_raw: modifiers.contains(&self.raw_operator),
+                        is_indent: modifiers.contains(&self.indent_operator),
+                        name: name.as_str().into(),
+                        column,
+                    }
+                }
+                None => {
+                    let name = capture
+                        .name("reference")
+                        .expect("tag regex captures a parameter or a reference name")
+                        .as_str();
+                    references.push((tag.range(), is_optional, name));
+                    ParametersLineToken::Reference {
+                        is_optional,
+                        name: name.into(),
+                        column,
+                    }
+                }
+            });
+            raw_start = tag.end();
+        }
+        match references.as_slice() {
+            [(range, is_optional, name)] if !has_parameters => Line::Reference {
+                left: Parser::unescape(line, 0..range.start, &escapes),
+                is_optional: *is_optional,
+                name: (*name).into(),
+                column: column(line, range.start),
+                right: Parser::unescape(line, range.end..line.len(), &escapes),
+            },
+            _ if !tokens.is_empty() => {
+                tokens.push(ParametersLineToken::Raw {
+                    value: line[raw_start..].into(),
+                });
+                Line::Parameters { tokens }
+            }
+            _ => Line::Raw { value: line.into() },
+        }
+    }
+    pub fn parse<'a>(&self, text: &'a str) -> Result<Template<'a>, Error> {
+        let (lines, line_endings) = LineEnding::split(text)
+            .map(|(line, line_ending)| (self.parse_line(line), line_ending))
+            .unzip();
+        Ok(Template::new(lines, line_endings))
+    }
+}
+
+#[test]
+fn test_same_as_parser() {
+    let syntax = Syntax::default();
+    let parser = crate::Parser::from_syntax(&syntax, "param", "ref").unwrap();
+    let regex_parser = RegexParser::from_syntax(&syntax, "param", "ref").unwrap();
+    let text = [
+        "plain text",
+        "",
+        "<!-- (param)p -->",
+        "a <!-- (param)p1 --> b <!--(optional)(param)p2--> c <!-- (param)p3 -->",
+        "<!-- not a tag --> <!-- (param)p -->",
+        "<!--<!-- (param)p --> tail",
+        "<!-- (ref)r --> <!-- (param)p -->",
+        "<!-- (param)p --><!-- (optional)(ref)r -->",
+        "<!-- (ref)R -->",
+        "    <!-- (optional)(ref)R -->",
+        "left <!-- (ref)R1 --> middle <!-- (ref)R2 --> right",
+        "<!-- (param)bad name --> <!-- (param) -->",
+        "юникод <!-- (param)имя --> ещё",
+        "<!-- (ref)pages.Row --> <!-- (ref)pages/Row -->",
+        "<!-- (raw)(param)p --> <!--(optional)(raw)(param)p--> <!-- (raw)(optional)(ref)r -->",
+        "<!-- (raw)(raw)(param)p --> <!-- (raw) (param)p -->",
+        "<!-- (indent)(param)p --> <!--(raw)(indent)(optional)(param)p--> <!-- (indent)(ref)r -->",
+        "<!-- (escape)(param)p --> <!-- (escape)(escape)(optional)(param)p --> <!-- (param)q -->",
+        "left <!-- (escape)(ref)R --> <!-- (ref)R --> right <!--(escape)(ref)R-->",
+        "<!-- (escape) (param)p --> <!-- (escape)(escape) -->",
+        "<!-- (param)pages.Row --> <!-- (ref)pages. --> <!-- (ref)pages/Row/ -->",
+    ]
+    .join("\n");
+    assert_eq!(
+        parser.parse(&text).unwrap(),
+        regex_parser.parse(&text).unwrap()
+    );
+}
+
+#[test]
+fn test_same_as_parser_on_generated_lines() {
+    let fragments = [
+        "<!--",
+        "-->",
+        " ",
+        "(param)",
+        "(ref)",
+        "(optional)",
+        "(raw)",
+        "(indent)",
+        "(escape)",
+        "name",
+        "x",
+        "<",
+        "-",
+        "(",
+        ")",
+        "й",
+        ".",
+        "/",
+    ];
+    let syntax = Syntax::default();
+    let parser = crate::Parser::from_syntax(&syntax, "param", "ref").unwrap();
+    let regex_parser = RegexParser::from_syntax(&syntax, "param", "ref").unwrap();
+    let mut state = 1_u64;
+    for _ in 0..2000 {
+        let mut line = String::new();
+        for _ in 0..12 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            line.push_str(fragments[(state >> 33) as usize % fragments.len()]);
+        }
+        assert_eq!(
+            parser.parse(&line).unwrap(),
+            regex_parser.parse(&line).unwrap(),
+            "{line}"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+/// Parameter value. Strings can be borrowed, other values are formatted straight into
+/// the output instead of being converted to `String` first.
+#[derive(Clone)]
+pub enum Scalar<'a> {
+    Str(Cow<'a, str>),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    /// Kept apart from [`Scalar::Float`], widening to `f64` would print `0.1_f32` as
+    /// `0.10000000149011612`.
+    Float32(f32),
+    Bool(bool),
+    Display(Arc<dyn fmt::Display + Send + Sync + 'a>),
+}
+impl<'a> Scalar<'a> {
+    /// Wraps any `Display` type, e.g. a date, formatted when rendered.
+    pub fn display(value: impl fmt::Display + Send + Sync + 'a) -> Self {
+        Scalar::Display(Arc::new(value))
+    }
+    /// Same value borrowing from `self` instead of copying strings.
+    pub fn reborrow(&self) -> Scalar<'_> {
+        match self {
+            Scalar::Str(value) => Scalar::Str(Cow::Borrowed(value)),
+            Scalar::Int(value) => Scalar::Int(*value),
+            Scalar::UInt(value) => Scalar::UInt(*value),
+            Scalar::Float(value) => Scalar::Float(*value),
+            Scalar::Float32(value) => Scalar::Float32(*value),
+            Scalar::Bool(value) => Scalar::Bool(*value),
+            Scalar::Display(value) => Scalar::Display(value.clone()),
+        }
+    }
+}
+impl fmt::Display for Scalar<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scalar::Str(value) => f.write_str(value),
+            Scalar::Int(value) => value.fmt(f),
+            Scalar::UInt(value) => value.fmt(f),
+            Scalar::Float(value) => value.fmt(f),
+            Scalar::Float32(value) => value.fmt(f),
+            Scalar::Bool(value) => value.fmt(f),
+            Scalar::Display(value) => value.fmt(f),
+        }
+    }
+}
+impl fmt::Debug for Scalar<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scalar::Str(value) => f.debug_tuple("Str").field(value).finish(),
+            Scalar::Int(value) => f.debug_tuple("Int").field(value).finish(),
+            Scalar::UInt(value) => f.debug_tuple("UInt").field(value).finish(),
+            Scalar::Float(value) => f.debug_tuple("Float").field(value).finish(),
+            Scalar::Float32(value) => f.debug_tuple("Float32").field(value).finish(),
+            Scalar::Bool(value) => f.debug_tuple("Bool").field(value).finish(),
+            Scalar::Display(value) => f.debug_tuple("Display").field(&value.to_string()).finish(),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Scalar<'a> {
+    fn from(value: &'a str) -> Self {
+        Scalar::Str(Cow::Borrowed(value))
+    }
+}
+impl<'a> From<&'a String> for Scalar<'a> {
+    fn from(value: &'a String) -> Self {
+        Scalar::Str(Cow::Borrowed(value))
+    }
+}
+impl From<String> for Scalar<'_> {
+    fn from(value: String) -> Self {
+        Scalar::Str(Cow::Owned(value))
+    }
+}
+impl<'a> From<Cow<'a, str>> for Scalar<'a> {
+    fn from(value: Cow<'a, str>) -> Self {
+        Scalar::Str(value)
+    }
+}
+impl From<char> for Scalar<'_> {
+    fn from(value: char) -> Self {
+        Scalar::Str(Cow::Owned(value.to_string()))
+    }
+}
+impl From<bool> for Scalar<'_> {
+    fn from(value: bool) -> Self {
+        Scalar::Bool(value)
+    }
+}
+
+macro_rules! impl_from_number {
+    ($variant:ident, $target:ty, $($source:ty),*) => {
+        $(
+            impl From<$source> for Scalar<'_> {
+                fn from(value: $source) -> Self {
+                    Scalar::$variant(value as $target)
+                }
+            }
+        )*
+    };
+}
+impl_from_number!(Int, i64, i8, i16, i32, i64, isize);
+impl_from_number!(UInt, u64, u8, u16, u32, u64, usize);
+impl_from_number!(Float, f64, f64);
+impl_from_number!(Float32, f32, f32);
+
+#[test]
+fn test_scalar() {
+    let owned = String::from("owned");
+    assert_eq!(
+        [
+            Scalar::from("str"),
+            Scalar::from(&owned),
+            Scalar::from(-1_i8),
+            Scalar::from(2_usize),
+            Scalar::from(0.5_f32),
+            Scalar::from(0.1_f32),
+            Scalar::from(0.1_f64),
+            Scalar::from(true),
+            Scalar::display(std::net::Ipv4Addr::LOCALHOST),
+        ]
+        .iter()
+        .map(|scalar| scalar.reborrow().to_string())
+        .collect::<Vec<_>>(),
+        vec![
+            "str",
+            "owned",
+            "-1",
+            "2",
+            "0.5",
+            "0.1",
+            "0.1",
+            "true",
+            "127.0.0.1"
+        ]
+    );
+}
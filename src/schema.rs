@@ -0,0 +1,342 @@
+use crate::{Line, Location, ParametersLineToken, Template, Templates};
+use std::collections::BTreeMap;
+
+/// How a parameter name is used by a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// `(param)`, accepts `Value` or `ValuesVec`.
+    Value,
+    /// `(ref)` to the template of the same name, accepts `Parameters` or `ParametersVec`
+    /// shaped by [`Schema::templates`].
+    Reference,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub kind: FieldKind,
+    /// Only when every tag using the name is optional.
+    pub is_optional: bool,
+    /// Where the name is first used.
+    pub location: Location,
+}
+
+/// Parameters of one template, by name.
+pub type TemplateSchema = BTreeMap<String, Field>;
+
+/// Same name used both as a parameter and as a reference in one template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub name: String,
+    pub parameter: Location,
+    pub reference: Location,
+}
+
+/// Parameters expected by a template and by every template reachable from it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Schema {
+    pub root: TemplateSchema,
+    /// Referenced templates found in [`Templates`], by name.
+    pub templates: BTreeMap<String, TemplateSchema>,
+    pub conflicts: Vec<Conflict>,
+}
+
+pub(crate) fn template_schema(
+    template: &Template,
+    references: &[&str],
+    conflicts: &mut Vec<Conflict>,
+) -> TemplateSchema {
+    let mut result = TemplateSchema::new();
+    let mut reported = Vec::new();
+    for (line_index, line) in template.lines.iter().enumerate() {
+        let mut uses = Vec::new();
+        match line {
+            Line::Raw { .. } => {}
+            Line::Parameters { tokens } => {
+                for token in tokens {
+                    match token {
+                        ParametersLineToken::Raw { .. } => {}
+                        ParametersLineToken::Parameter {
+                            is_optional,
+                            name,
+                            column,
+                            ..
+                        } => uses.push((FieldKind::Value, name.as_ref(), *is_optional, *column)),
+                        ParametersLineToken::Reference {
+                            is_optional,
+                            name,
+                            column,
+                        } => {
+                            uses.push((FieldKind::Reference, name.as_ref(), *is_optional, *column))
+                        }
+                    }
+                }
+            }
+            Line::Reference {
+                is_optional,
+                name,
+                column,
+                ..
+            } => uses.push((FieldKind::Reference, name.as_ref(), *is_optional, *column)),
+        }
+        for (kind, name, is_optional, column) in uses {
+            let location = Location::new(references, line_index + 1, column);
+            match result.get_mut(name) {
+                None => {
+                    result.insert(
+                        name.to_string(),
+                        Field {
+                            kind,
+                            is_optional,
+                            location,
+                        },
+                    );
+                }
+                Some(field) if field.kind == kind => field.is_optional &= is_optional,
+                Some(field) => {
+                    if !reported.contains(&name) {
+                        reported.push(name);
+                        let (parameter, reference) = match kind {
+                            FieldKind::Value => (location, field.location.clone()),
+                            FieldKind::Reference => (field.location.clone(), location),
+                        };
+                        conflicts.push(Conflict {
+                            name: name.to_string(),
+                            parameter,
+                            reference,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+impl Template<'_> {
+    /// Infers which parameters this template and the templates it references expect.
+    pub fn schema(&self, templates: &Templates) -> Schema {
+        let mut conflicts = Vec::new();
+        let root = template_schema(self, &[], &mut conflicts);
+        let mut schemas = BTreeMap::new();
+        let mut pending: Vec<String> = references(&root).collect();
+        while let Some(name) = pending.pop() {
+            if schemas.contains_key(&name) {
+                continue;
+            }
+            if let Some(template) = templates.get(name.as_str()) {
+                let schema = template_schema(template, &[name.as_str()], &mut conflicts);
+                pending.extend(references(&schema));
+                schemas.insert(name, schema);
+            }
+        }
+        Schema {
+            root,
+            templates: schemas,
+            conflicts,
+        }
+    }
+}
+
+fn references(schema: &TemplateSchema) -> impl Iterator<Item = String> + '_ {
+    schema
+        .iter()
+        .filter(|(_, field)| field.kind == FieldKind::Reference)
+        .map(|(name, _)| name.clone())
+}
+
+#[cfg(feature = "serde")]
+impl Schema {
+    fn json_schema_object(
+        schema: &TemplateSchema,
+        templates: &BTreeMap<String, TemplateSchema>,
+    ) -> serde_json::Value {
+        let properties: serde_json::Map<String, serde_json::Value> = schema
+            .iter()
+            .map(|(name, field)| {
+                let item = match field.kind {
+                    FieldKind::Value => {
+                        serde_json::json!({"type": ["string", "number", "boolean"]})
+                    }
+                    FieldKind::Reference if templates.contains_key(name) => {
+                        // Namespaced names like `pages/Row` are escaped as JSON Pointer segments.
+                        let segment = name.replace('~', "~0").replace('/', "~1");
+                        serde_json::json!({"$ref": format!("#/$defs/{segment}")})
+                    }
+                    FieldKind::Reference => serde_json::json!({"type": "object"}),
+                };
+                let mut array = serde_json::json!({"type": "array", "items": item});
+                if field.kind == FieldKind::Value {
+                    array["minItems"] = 1.into();
+                }
+                let mut one_of = vec![item, array];
+                // `null` members are treated as absent keys.
+                if field.is_optional {
+                    one_of.push(serde_json::json!({"type": "null"}));
+                }
+                (name.clone(), serde_json::json!({"oneOf": one_of}))
+            })
+            .collect();
+        let required: Vec<&String> = schema
+            .iter()
+            .filter(|(_, field)| !field.is_optional)
+            .map(|(name, _)| name)
+            .collect();
+        serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+    /// JSON Schema (draft 2020-12) of the data accepted by `parameters_from_json` for this template,
+    /// with referenced templates in `$defs`.
+    pub fn to_json_schema(&self) -> serde_json::Value {
+        let mut result = serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+        });
+        if let (serde_json::Value::Object(result), serde_json::Value::Object(root)) = (
+            &mut result,
+            Self::json_schema_object(&self.root, &self.templates),
+        ) {
+            result.extend(root);
+        }
+        if !self.templates.is_empty() {
+            result["$defs"] = self
+                .templates
+                .iter()
+                .map(|(name, schema)| {
+                    (
+                        name.clone(),
+                        Self::json_schema_object(schema, &self.templates),
+                    )
+                })
+                .collect::<serde_json::Map<_, _>>()
+                .into();
+        }
+        result
+    }
+}
+
+#[test]
+fn test_schema() {
+    let parser = crate::Parser::from_syntax(&crate::Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([
+        (
+            "Row",
+            parser
+                .parse("<tr title=\"<!-- (optional)(param)title -->\">\n  <td><!-- (param)cell --></td>\n  <!-- (optional)(ref)Row -->\n</tr>")
+                .unwrap(),
+        ),
+        (
+            "Unused",
+            parser.parse("<!-- (param)unused -->").unwrap(),
+        ),
+    ]);
+    let template = parser
+        .parse("<!-- (optional)(param)caption --><!-- (param)Footer -->\n<!-- (ref)Row -->\n<!-- (param)caption -->\n<!-- (ref)Footer -->")
+        .unwrap();
+    let schema = template.schema(&templates);
+    let field = |schema: &TemplateSchema, name: &str| {
+        let field = &schema[name];
+        (field.kind, field.is_optional)
+    };
+    assert_eq!(
+        schema.root.keys().collect::<Vec<_>>(),
+        vec!["Footer", "Row", "caption"]
+    );
+    assert_eq!(field(&schema.root, "caption"), (FieldKind::Value, false));
+    assert_eq!(field(&schema.root, "Row"), (FieldKind::Reference, false));
+    assert_eq!(schema.templates.keys().collect::<Vec<_>>(), vec!["Row"]);
+    assert_eq!(
+        field(&schema.templates["Row"], "title"),
+        (FieldKind::Value, true)
+    );
+    assert_eq!(
+        field(&schema.templates["Row"], "cell"),
+        (FieldKind::Value, false)
+    );
+    assert_eq!(
+        field(&schema.templates["Row"], "Row"),
+        (FieldKind::Reference, true)
+    );
+    assert_eq!(
+        schema.conflicts,
+        vec![Conflict {
+            name: "Footer".to_string(),
+            parameter: Location {
+                references: Vec::new(),
+                line: 1,
+                column: 34
+            },
+            reference: Location {
+                references: Vec::new(),
+                line: 4,
+                column: 1
+            },
+        }]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_json_schema() {
+    let parser = crate::Parser::from_syntax(&crate::Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([(
+        "Row",
+        parser
+            .parse(
+                "<tr>\n  <td><!-- (param)cell --></td>\n  <!-- (optional)(ref)Tooltip -->\n</tr>",
+            )
+            .unwrap(),
+    )]);
+    let template = parser
+        .parse("<table>\n  <!-- (ref)Row -->\n</table><!-- (optional)(param)caption -->")
+        .unwrap();
+    assert_eq!(
+        template.schema(&templates).to_json_schema(),
+        serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": {
+                "Row": {"oneOf": [
+                    {"$ref": "#/$defs/Row"},
+                    {"type": "array", "items": {"$ref": "#/$defs/Row"}}
+                ]},
+                "caption": {"oneOf": [
+                    {"type": ["string", "number", "boolean"]},
+                    {"type": "array", "items": {"type": ["string", "number", "boolean"]}, "minItems": 1},
+                    {"type": "null"}
+                ]}
+            },
+            "required": ["Row"],
+            "$defs": {
+                "Row": {
+                    "type": "object",
+                    "properties": {
+                        "Tooltip": {"oneOf": [
+                            {"type": "object"},
+                            {"type": "array", "items": {"type": "object"}},
+                            {"type": "null"}
+                        ]},
+                        "cell": {"oneOf": [
+                            {"type": ["string", "number", "boolean"]},
+                            {"type": "array", "items": {"type": ["string", "number", "boolean"]}, "minItems": 1}
+                        ]}
+                    },
+                    "required": ["cell"]
+                }
+            }
+        })
+    );
+
+    let templates = Templates::from([("pages/Row", parser.parse("<tr></tr>").unwrap())]);
+    let template = parser.parse("<!-- (ref)pages/Row -->").unwrap();
+    let schema = template.schema(&templates).to_json_schema();
+    assert_eq!(
+        schema["properties"]["pages/Row"]["oneOf"][0],
+        serde_json::json!({"$ref": "#/$defs/pages~1Row"})
+    );
+    assert_eq!(
+        schema.pointer("/$defs/pages~1Row"),
+        Some(&serde_json::json!({"type": "object", "properties": {}, "required": []}))
+    );
+}
@@ -0,0 +1,141 @@
+use crate::{
+    Error, Issue, Parser, Problem, RenderOptions, Schema, Syntax, Template, TemplateParameters,
+    Templates, validate,
+};
+use std::collections::HashMap;
+
+/// Parsed templates together with the [`Parser`] for them.
+///
+/// Unlike [`Templates`] it owns everything, so it can be built once and shared
+/// between threads, e.g. in an `Arc`. Templates are parsed once when added, rendering
+/// only puts the ones reachable from the rendered template through references into
+/// [`Templates`], which does not copy them.
+#[derive(Debug)]
+pub struct TemplateSet {
+    parser: Parser,
+    templates: HashMap<String, Template<'static>>,
+}
+impl Default for TemplateSet {
+    fn default() -> Self {
+        TemplateSet::new(
+            Parser::from_syntax(&Syntax::default(), "param", "ref")
+                .expect("default syntax is valid"),
+        )
+    }
+}
+impl TemplateSet {
+    pub fn new(parser: Parser) -> Self {
+        TemplateSet {
+            parser,
+            templates: HashMap::new(),
+        }
+    }
+    pub fn parser(&self) -> &Parser {
+        &self.parser
+    }
+    /// Parses `source` and adds or replaces template `name` with it.
+    pub fn add(&mut self, name: impl Into<String>, source: impl AsRef<str>) -> Result<(), Error> {
+        let template = self.parser.parse_owned(source)?;
+        self.templates.insert(name.into(), template);
+        Ok(())
+    }
+    pub fn remove(&mut self, name: &str) -> Option<Template<'static>> {
+        self.templates.remove(name)
+    }
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.templates.keys().map(String::as_str)
+    }
+    pub fn get(&self, name: &str) -> Option<&Template<'static>> {
+        self.templates.get(name)
+    }
+    /// Template `name` and every template it references, directly or not.
+    pub fn templates(&self, name: &str) -> Result<(&Template<'static>, Templates<'_>), Error> {
+        let template = self.get(name).ok_or_else(|| Error::UnknownTemplate {
+            name: name.to_string(),
+        })?;
+        let mut templates = Templates::new();
+        let mut pending: Vec<&str> = referenced_names(template).collect();
+        while let Some(reference) = pending.pop() {
+            if templates.contains_key(reference) {
+                continue;
+            }
+            if let Some((reference, subtemplate)) = self.templates.get_key_value(reference) {
+                pending.extend(referenced_names(subtemplate));
+                templates.insert(reference.as_str(), subtemplate.clone());
+            }
+        }
+        Ok((template, templates))
+    }
+    /// Runs [`validate`] over all templates in the set.
+    pub fn validate(&self) -> Vec<Problem> {
+        let templates: Templates = self
+            .templates
+            .iter()
+            .map(|(name, template)| (name.as_str(), template.clone()))
+            .collect();
+        validate(&templates)
+    }
+    pub fn schema(&self, name: &str) -> Result<Schema, Error> {
+        let (template, templates) = self.templates(name)?;
+        Ok(template.schema(&templates))
+    }
+    pub fn check(&self, name: &str, parameters: &TemplateParameters) -> Result<Vec<Issue>, Error> {
+        let (template, templates) = self.templates(name)?;
+        Ok(template.check(parameters, &templates))
+    }
+    pub fn render(&self, name: &str, parameters: &TemplateParameters) -> Result<String, Error> {
+        self.render_with(name, parameters, &RenderOptions::default())
+    }
+    pub fn render_with(
+        &self,
+        name: &str,
+        parameters: &TemplateParameters,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
+        let (template, templates) = self.templates(name)?;
+        template.render_with(parameters, &templates, options)
+    }
+}
+
+fn referenced_names<'a>(template: &'a Template) -> impl Iterator<Item = &'a str> {
+    template.references().map(|reference| reference.name)
+}
+
+#[test]
+fn test_template_set() {
+    use crate::{TemplateParametersValue, params, tp_params_vec, tp_values};
+
+    let mut set = TemplateSet::default();
+    set.add("Table", "<table>\n    <!-- (ref)Row -->\n</table>")
+        .unwrap();
+    set.add("Row", "<tr>\n    <td><!-- (param)cell --></td>\n</tr>")
+        .unwrap();
+    set.add("Unused", "<!-- (param)p -->").unwrap();
+    assert_eq!(set.validate(), Vec::new());
+
+    let (_, templates) = set.templates("Table").unwrap();
+    assert_eq!(templates.keys().collect::<Vec<_>>(), vec![&"Row"]);
+    assert!(std::ptr::eq(
+        templates["Row"].lines(),
+        set.get("Row").unwrap().lines()
+    ));
+
+    let set = std::sync::Arc::new(set);
+    let handle = std::thread::spawn({
+        let set = set.clone();
+        move || {
+            set.render(
+                "Table",
+                &params! {"Row" => tp_params_vec!(params! {"cell" => tp_values!("1", "2")})},
+            )
+        }
+    });
+    assert_eq!(
+        handle.join().unwrap().unwrap(),
+        "<table>\n    <tr>\n        <td>1</td>\n        <td>2</td>\n    </tr>\n</table>"
+    );
+    assert!(matches!(
+        set.render("Missing", &TemplateParameters::new()),
+        Err(Error::UnknownTemplate { .. })
+    ));
+}
@@ -0,0 +1,190 @@
+use crate::{Location, Templates};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Issue found by [`validate`] without rendering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// Non-optional reference to a template missing from [`Templates`];
+    /// `location.template()` is the referencing template.
+    UnresolvedReference { name: String, location: Location },
+    /// Templates referencing each other in a loop, in reference order starting
+    /// from the alphabetically first one. Fine for intentionally recursive templates,
+    /// whose depth can then be bounded with [`RenderOptions::max_depth`](crate::RenderOptions::max_depth).
+    Cycle { templates: Vec<String> },
+}
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::UnresolvedReference { name, location } => write!(
+                f,
+                "No template provided for template reference \"{name}\" at {location}"
+            ),
+            Problem::Cycle { templates } => write!(
+                f,
+                "Templates reference each other in a cycle: {} -> {}",
+                templates.join(" -> "),
+                templates[0]
+            ),
+        }
+    }
+}
+
+struct Graph<'a> {
+    edges: BTreeMap<&'a str, BTreeSet<&'a str>>,
+    index: BTreeMap<&'a str, usize>,
+    lowlink: BTreeMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    components: Vec<Vec<&'a str>>,
+}
+impl<'a> Graph<'a> {
+    /// Tarjan's strongly connected components algorithm.
+    fn connect(&mut self, node: &'a str) {
+        let index = self.index.len();
+        self.index.insert(node, index);
+        self.lowlink.insert(node, index);
+        self.stack.push(node);
+        for &next in self.edges[node].clone().iter() {
+            if !self.index.contains_key(next) {
+                self.connect(next);
+                let lowlink = self.lowlink[node].min(self.lowlink[next]);
+                self.lowlink.insert(node, lowlink);
+            } else if self.stack.contains(&next) {
+                let lowlink = self.lowlink[node].min(self.index[next]);
+                self.lowlink.insert(node, lowlink);
+            }
+        }
+        if self.lowlink[node] == self.index[node] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+/// Orders a cycle's members by following references from the first of them.
+fn cycle_order<'a>(
+    edges: &BTreeMap<&'a str, BTreeSet<&'a str>>,
+    members: &[&'a str],
+) -> Vec<String> {
+    let members: BTreeSet<&str> = members.iter().copied().collect();
+    let first = *members.first().expect("components are not empty");
+    let mut result = vec![first];
+    let mut current = first;
+    while let Some(&next) = edges[current]
+        .iter()
+        .find(|next| members.contains(*next) && !result.contains(*next))
+    {
+        result.push(next);
+        current = next;
+    }
+    for member in members {
+        if !result.contains(&member) {
+            result.push(member);
+        }
+    }
+    result.into_iter().map(str::to_string).collect()
+}
+
+/// Checks every reference in `templates` at once: reports non-optional references
+/// to missing templates and reference cycles, sorted by template name.
+pub fn validate(templates: &Templates) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut names: Vec<&str> = templates.keys().copied().collect();
+    names.sort_unstable();
+    let mut edges: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for &template_name in &names {
+        let template_edges = edges.entry(template_name).or_default();
+        for reference in templates[template_name].references() {
+            if templates.contains_key(reference.name) {
+                template_edges.insert(reference.name);
+            } else if !reference.is_optional {
+                problems.push(Problem::UnresolvedReference {
+                    name: reference.name.to_string(),
+                    location: Location::new(&[template_name], reference.line, reference.column),
+                });
+            }
+        }
+    }
+    let mut graph = Graph {
+        edges,
+        index: BTreeMap::new(),
+        lowlink: BTreeMap::new(),
+        stack: Vec::new(),
+        components: Vec::new(),
+    };
+    for &name in &names {
+        if !graph.index.contains_key(name) {
+            graph.connect(name);
+        }
+    }
+    let mut cycles: Vec<Vec<String>> = graph
+        .components
+        .iter()
+        .filter(|component| component.len() > 1 || graph.edges[component[0]].contains(component[0]))
+        .map(|component| cycle_order(&graph.edges, component))
+        .collect();
+    cycles.sort();
+    problems.extend(
+        cycles
+            .into_iter()
+            .map(|templates| Problem::Cycle { templates }),
+    );
+    problems
+}
+
+#[test]
+fn test_validate() {
+    let parser = crate::Parser::from_syntax(&crate::Syntax::default(), "param", "ref").unwrap();
+    let templates = Templates::from([
+        (
+            "Table",
+            parser
+                .parse("<table>\n  <!-- (ref)Row -->\n  <!-- (ref)Footer -->\n</table>")
+                .unwrap(),
+        ),
+        (
+            "Row",
+            parser
+                .parse("<tr><!-- (ref)Cell --></tr>\n<!-- (optional)(ref)Tooltip -->")
+                .unwrap(),
+        ),
+        ("Cell", parser.parse("<td><!-- (ref)Row --></td>").unwrap()),
+        (
+            "Menu",
+            parser
+                .parse("<ul>\n  <!-- (optional)(ref)Menu -->\n</ul>")
+                .unwrap(),
+        ),
+        ("Leaf", parser.parse("<!-- (param)value -->").unwrap()),
+    ]);
+    let problems = validate(&templates);
+    assert_eq!(
+        problems,
+        vec![
+            Problem::UnresolvedReference {
+                name: "Footer".to_string(),
+                location: Location {
+                    references: vec!["Table".to_string()],
+                    line: 3,
+                    column: 3
+                }
+            },
+            Problem::Cycle {
+                templates: vec!["Cell".to_string(), "Row".to_string()]
+            },
+            Problem::Cycle {
+                templates: vec!["Menu".to_string()]
+            },
+        ]
+    );
+    assert_eq!(
+        problems[1].to_string(),
+        "Templates reference each other in a cycle: Cell -> Row -> Cell"
+    );
+}
@@ -0,0 +1,96 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn directory(name: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("drunk_snail_test_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn run(directory: &Path, template: &str, data: &str, arguments: &[&str]) -> Output {
+    fs::write(directory.join("template.html"), template).unwrap();
+    fs::write(directory.join("data.json"), data).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_drunk_snail"))
+        .current_dir(directory)
+        .args(["template.html", "--data", "data.json"])
+        .args(arguments)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_exit_codes() {
+    let directory = directory("exit_codes");
+
+    let output = run(
+        &directory,
+        "<p><!-- (param)text --></p>\n",
+        r#"{"text": "a"}"#,
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"<p>a</p>\n");
+
+    let output = run(
+        &directory,
+        "<table>\n<!-- (ref)Row -->\n</table>\n",
+        r#"{"Row": [{}]}"#,
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stdout.is_empty());
+
+    let output = run(&directory, "<p>\n<!-- (param)text -->\n</p>\n", "{}", &[]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(output.stdout.is_empty());
+
+    let output = run(
+        &directory,
+        "<p><!-- (param)text --></p>\n",
+        r#"{"text": "a", "title": "b"}"#,
+        &["--unused-parameters", "error"],
+    );
+    assert_eq!(output.status.code(), Some(4));
+    assert!(output.stdout.is_empty());
+
+    let output = run(&directory, "", "{}", &["--templates", "missing"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(output.stdout.is_empty());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_failure_keeps_output_file() {
+    let directory = directory("output_file");
+    fs::write(directory.join("out.html"), "previous").unwrap();
+
+    let output = run(
+        &directory,
+        "<p>\n<!-- (param)text -->\n</p>\n",
+        "{}",
+        &["--output", "out.html"],
+    );
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(
+        fs::read_to_string(directory.join("out.html")).unwrap(),
+        "previous"
+    );
+
+    let output = run(
+        &directory,
+        "<p><!-- (param)text --></p>\n",
+        r#"{"text": "a"}"#,
+        &["--output", "out.html"],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(directory.join("out.html")).unwrap(),
+        "<p>a</p>\n"
+    );
+
+    fs::remove_dir_all(&directory).unwrap();
+}